@@ -0,0 +1,116 @@
+pub mod autocomplete {
+    use serde_json::{json, Value};
+    use crate::init_commands::init_commands::CommandOptionType;
+    use crate::interaction_endpoint::command_handler::Interaction;
+
+    // discord rejects autocomplete responses with more choices than this
+    const MAX_CHOICES: usize = 25;
+    const MAX_CHOICE_NAME_LENGTH: usize = 100;
+    const MAX_CHOICE_VALUE_LENGTH: usize = 100;
+
+    /// Builds the type 8 response with the suggestions for the option the user is typing in
+    pub fn respond(interaction: &Interaction) -> Value {
+        json!({
+            "type": 8,
+            "data": {
+                "choices": choices(interaction)
+            }
+        })
+    }
+
+    fn choices(interaction: &Interaction) -> Vec<Value> {
+        let Some(data) = &interaction.data else {
+            return vec![];
+        };
+        let Some(focused) = data.leaf_options().iter().find(|option| option.focused.unwrap_or(false)) else {
+            return vec![];
+        };
+        let Some(command) = data.find_command() else {
+            return vec![];
+        };
+        let Some(option) = command.options.iter().find(|option| option.name == focused.name) else {
+            return vec![];
+        };
+        let typed = match &focused.value {
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => String::new()
+        };
+
+        filter(option.suggestions(), typed.as_str())
+            .into_iter()
+            .filter_map(|suggestion| {
+                // numeric options need numeric values
+                let value = match option.r#type {
+                    CommandOptionType::Integer => Value::from(suggestion.parse::<i64>().ok()?),
+                    CommandOptionType::Number => Value::from(suggestion.parse::<f64>().ok()?),
+                    _ => Value::from(suggestion.clone())
+                };
+                let name: String = suggestion.chars().take(MAX_CHOICE_NAME_LENGTH).collect();
+                Some(json!({"name": name, "value": value}))
+            })
+            .take(MAX_CHOICES)
+            .collect()
+    }
+
+    /// Orders the suggestions matching what has been typed so far, prefix matches first,
+    /// then ones containing it and lastly ones containing its characters in order.
+    /// Suggestions too long to be a choice's value are left out, as a single one makes discord refuse them all
+    pub fn filter(suggestions: Vec<String>, typed: &str) -> Vec<String> {
+        let typed = typed.trim().to_lowercase();
+        let mut ranked: Vec<(u8, String)> = suggestions.into_iter()
+            .filter(|suggestion| suggestion.chars().count() <= MAX_CHOICE_VALUE_LENGTH)
+            .filter_map(|suggestion| {
+                let lower = suggestion.to_lowercase();
+                let rank = if lower.starts_with(typed.as_str()) {
+                    0
+                } else if lower.contains(typed.as_str()) {
+                    1
+                } else if is_subsequence(typed.as_str(), lower.as_str()) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, suggestion))
+            })
+            .collect();
+        // the sort is stable so suggestions keep their configured order within a rank
+        ranked.sort_by_key(|(rank, _)| *rank);
+        ranked.into_iter().map(|(_, suggestion)| suggestion).collect()
+    }
+
+    fn is_subsequence(needle: &str, haystack: &str) -> bool {
+        let mut haystack = haystack.chars();
+        needle.chars().all(|c| haystack.any(|h| h == c))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::filter;
+
+        fn suggestions() -> Vec<String> {
+            ["videos", "news", "images", "Newsletter", "events"].iter().map(|suggestion| suggestion.to_string()).collect()
+        }
+
+        #[test]
+        fn ranks_prefixes_then_substrings_then_subsequences() {
+            assert_eq!(filter(suggestions(), "new"), vec!["news", "Newsletter"]);
+            // only images has "es" in one piece, the rest just have the letters in order
+            assert_eq!(filter(suggestions(), "es"), vec!["images", "videos", "news", "Newsletter", "events"]);
+            assert_eq!(filter(suggestions(), "ent"), vec!["events"]);
+            assert_eq!(filter(suggestions(), "ws"), vec!["news", "Newsletter"]);
+            assert_eq!(filter(suggestions(), " VS "), vec!["videos", "events"]);
+            assert_eq!(filter(suggestions(), "ns"), vec!["news", "Newsletter", "events"]);
+            assert!(filter(suggestions(), "xyz").is_empty());
+            // a value over 100 characters would make discord refuse every suggestion
+            let mut with_long_line = suggestions();
+            with_long_line.insert(0, format!("news {}", "x".repeat(100)));
+            assert_eq!(filter(with_long_line, "new"), vec!["news", "Newsletter"]);
+        }
+
+        #[test]
+        fn keeps_the_configured_order_when_nothing_is_typed() {
+            assert_eq!(filter(suggestions(), ""), suggestions());
+        }
+    }
+}
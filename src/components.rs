@@ -0,0 +1,36 @@
+pub mod components {
+    use serde_json::{json, Value};
+    use crate::init_commands::init_commands::{find_component_response, ComponentAction, EPHEMERAL};
+    use crate::interaction_endpoint::command_handler::Interaction;
+    use crate::limits::limits;
+    use crate::templates::templates::{render_message, TemplateContext};
+
+    /// Builds the response to a button being clicked or a select menu being used from its configured response.
+    /// Components without one are acknowledged without changing anything
+    pub fn respond(interaction: &Interaction) -> Value {
+        let custom_id = interaction.data.as_ref().and_then(|data| data.custom_id.as_deref());
+        let response = interaction.data.as_ref().zip(custom_id).and_then(|(data, custom_id)| {
+            let values = data.values.clone().unwrap_or_default();
+            find_component_response(custom_id, &values, interaction.locale.as_deref())
+        });
+        let (Some(response), Some(custom_id)) = (response, custom_id) else {
+            return json!({"type": 6});
+        };
+
+        let context = TemplateContext::from_interaction(interaction);
+        let mut message = render_message(&response.message, &context);
+        limits::fit_message(&mut message, custom_id);
+        let message = serde_json::to_value(message).unwrap();
+        match response.action {
+            ComponentAction::Message { ephemeral } => {
+                let mut data = message;
+                if ephemeral {
+                    data["flags"] = Value::from(response.message.flags | EPHEMERAL);
+                }
+                json!({"type": 4, "data": data})
+            }
+            ComponentAction::Update => json!({"type": 7, "data": message}),
+            ComponentAction::DeferUpdate => json!({"type": 6})
+        }
+    }
+}
@@ -0,0 +1,228 @@
+pub mod discord_client {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use lazy_static::lazy_static;
+    use reqwest::blocking::{Client, RequestBuilder, Response};
+    use reqwest::header::HeaderMap;
+    use reqwest::{Method, StatusCode, Url};
+    use serde_json::Value;
+
+    // how often a request is retried after being rate limited before giving up
+    const MAX_RATE_LIMIT_RETRIES: u8 = 5;
+
+    lazy_static! {
+        static ref SHARED: DiscordClient = DiscordClient::new();
+    }
+
+    /// The client every request to discord goes through, so they all share the same rate limits
+    pub fn shared() -> &'static DiscordClient {
+        &SHARED
+    }
+
+    struct Bucket {
+        remaining: u64,
+        reset_at: Instant
+    }
+
+    /// A REST client that keeps to discord's rate limits. Requests wait until their bucket
+    /// or the global limit has room again, and are sent again after a 429
+    pub struct DiscordClient {
+        client: Client,
+        // the bucket discord told us each route belongs to
+        routes: Mutex<HashMap<String, String>>,
+        buckets: Mutex<HashMap<String, Bucket>>,
+        global_reset_at: Mutex<Option<Instant>>
+    }
+
+    impl DiscordClient {
+        pub fn new() -> DiscordClient {
+            DiscordClient {
+                client: Client::new(),
+                routes: Mutex::new(HashMap::new()),
+                buckets: Mutex::new(HashMap::new()),
+                global_reset_at: Mutex::new(None),
+            }
+        }
+
+        /// Sends the request built by `build`, which is called again for every retry
+        pub fn send<F>(&self, build: F) -> reqwest::Result<Response> where F: Fn(&Client) -> RequestBuilder {
+            let mut retries = 0;
+            loop {
+                let request = build(&self.client).build()?;
+                let route = route(request.method(), request.url());
+                self.wait_for_room(route.as_str());
+
+                let response = self.client.execute(request)?;
+                self.update_bucket(route.as_str(), response.headers());
+                if response.status() != StatusCode::TOO_MANY_REQUESTS || retries >= MAX_RATE_LIMIT_RETRIES {
+                    return Ok(response);
+                }
+                retries += 1;
+
+                let global = response.headers().get("X-RateLimit-Global").is_some();
+                let body: Value = response.json().unwrap_or(Value::Null);
+                let retry_after = Duration::from_secs_f64(body["retry_after"].as_f64().unwrap_or(1.0));
+                let global = global || body["global"].as_bool().unwrap_or(false);
+                println!("Rate limited by discord on {}, retrying in {:?}", route, retry_after);
+                if global {
+                    *self.global_reset_at.lock().unwrap() = Some(Instant::now() + retry_after);
+                } else {
+                    thread::sleep(retry_after);
+                }
+            }
+        }
+
+        /// Blocks until the global limit and the route's bucket allow another request, taking up its spot
+        fn wait_for_room(&self, route: &str) {
+            loop {
+                let global_reset_at = *self.global_reset_at.lock().unwrap();
+                if let Some(reset_at) = global_reset_at {
+                    let now = Instant::now();
+                    if reset_at > now {
+                        thread::sleep(reset_at - now);
+                        continue;
+                    }
+                }
+
+                let bucket_id = self.routes.lock().unwrap().get(route).cloned();
+                let Some(bucket_id) = bucket_id else {
+                    return;
+                };
+                let mut buckets = self.buckets.lock().unwrap();
+                let Some(bucket) = buckets.get_mut(&bucket_id) else {
+                    return;
+                };
+                let now = Instant::now();
+                if bucket.reset_at <= now || bucket.remaining > 0 {
+                    // a reset bucket has room for at least this request until discord says otherwise
+                    bucket.remaining = bucket.remaining.saturating_sub(1);
+                    if bucket.reset_at <= now {
+                        bucket.reset_at = now;
+                    }
+                    return;
+                }
+                let wait = bucket.reset_at - now;
+                drop(buckets);
+                thread::sleep(wait);
+            }
+        }
+
+        fn update_bucket(&self, route: &str, headers: &HeaderMap) {
+            let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(|value| value.to_string());
+            let Some(bucket_id) = header("X-RateLimit-Bucket") else {
+                return;
+            };
+            // discord shares a bucket between routes, but keeps the limits of each major parameter apart
+            let bucket_id = bucket_id + major_parameters(route).as_str();
+            let remaining = header("X-RateLimit-Remaining").and_then(|value| value.parse::<u64>().ok());
+            let reset_after = header("X-RateLimit-Reset-After").and_then(|value| value.parse::<f64>().ok());
+            let mut buckets = self.buckets.lock().unwrap();
+            if let (Some(remaining), Some(reset_after)) = (remaining, reset_after) {
+                buckets.insert(bucket_id.clone(), Bucket {
+                    remaining,
+                    reset_at: Instant::now() + Duration::from_secs_f64(reset_after),
+                });
+            }
+            // every interaction token makes a route of its own, so forget the routes and buckets that
+            // have reset as they have room again anyway, otherwise they pile up for as long as the bot runs
+            let now = Instant::now();
+            buckets.retain(|_, bucket| bucket.reset_at > now);
+            let mut routes = self.routes.lock().unwrap();
+            routes.insert(route.to_string(), bucket_id);
+            routes.retain(|_, bucket_id| buckets.contains_key(bucket_id));
+        }
+    }
+
+    impl Default for DiscordClient {
+        fn default() -> Self {
+            DiscordClient::new()
+        }
+    }
+
+    /// The rate limit route of a request, its method and path with every id replaced
+    /// except the major parameters that discord gives their own limits
+    pub fn route(method: &Method, url: &Url) -> String {
+        let mut route = method.to_string();
+        let mut previous = "";
+        for segment in url.path_segments().into_iter().flatten() {
+            let is_id = !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit());
+            route.push('/');
+            if is_id && !matches!(previous, "channels" | "guilds" | "webhooks") {
+                route.push_str(":id");
+            } else {
+                route.push_str(segment);
+            }
+            previous = segment;
+        }
+        route
+    }
+
+    /// The major parameters in a route, such as the channel id or the webhook id and token
+    fn major_parameters(route: &str) -> String {
+        let mut parameters = String::new();
+        let mut take = 0;
+        for segment in route.split('/') {
+            if take > 0 {
+                parameters.push('/');
+                parameters.push_str(segment);
+                take -= 1;
+            }
+            take = match segment {
+                "channels" | "guilds" => 1,
+                "webhooks" => 2,
+                _ => take
+            };
+        }
+        parameters
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::thread;
+        use std::time::Duration;
+        use reqwest::header::HeaderMap;
+        use reqwest::{Method, StatusCode, Url};
+        use crate::mock_discord::mock_discord::MockDiscord;
+        use super::{route, DiscordClient};
+
+        #[test]
+        fn keeps_major_parameters_in_routes() {
+            let url = Url::parse("https://discord.com/api/webhooks/1/token/messages/@original").unwrap();
+            assert_eq!(route(&Method::PATCH, &url), "PATCH/api/webhooks/1/token/messages/@original");
+            let url = Url::parse("https://discord.com/api/applications/1/guilds/2/commands/3").unwrap();
+            assert_eq!(route(&Method::DELETE, &url), "DELETE/api/applications/:id/guilds/2/commands/:id");
+        }
+
+        #[test]
+        fn retries_after_being_rate_limited() {
+            let discord = MockDiscord::start();
+            discord.rate_limit_next(2);
+            let client = DiscordClient::new();
+            let response = client.send(|client| client.get(discord.url.clone() + "/applications/@me")).unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(discord.requests().len(), 3);
+        }
+
+        #[test]
+        fn forgets_routes_once_their_bucket_resets() {
+            let headers = |bucket: &str, reset_after: &str| {
+                let mut headers = HeaderMap::new();
+                headers.insert("X-RateLimit-Bucket", bucket.parse().unwrap());
+                headers.insert("X-RateLimit-Remaining", "4".parse().unwrap());
+                headers.insert("X-RateLimit-Reset-After", reset_after.parse().unwrap());
+                headers
+            };
+            let client = DiscordClient::new();
+            client.update_bucket("PATCH/api/webhooks/1/first-token/messages/@original", &headers("webhook", "0.01"));
+            client.update_bucket("GET/api/applications/@me", &headers("application", "60"));
+            thread::sleep(Duration::from_millis(20));
+            client.update_bucket("PATCH/api/webhooks/1/second-token/messages/@original", &headers("webhook", "60"));
+
+            let mut routes: Vec<String> = client.routes.lock().unwrap().keys().cloned().collect();
+            routes.sort();
+            assert_eq!(routes, vec!["GET/api/applications/@me", "PATCH/api/webhooks/1/second-token/messages/@original"]);
+        }
+    }
+}
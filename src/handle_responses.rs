@@ -1,40 +1,131 @@
 pub mod handle_responses {
-    use std::sync::mpsc::{Receiver};
+    use std::collections::VecDeque;
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use std::fs;
+    use std::path::Path;
+    use reqwest::blocking::{Client, RequestBuilder};
+    use reqwest::blocking::multipart::{Form, Part};
     use reqwest::StatusCode;
     use threadpool::ThreadPool;
-    use crate::init_commands::init_commands::{get_application_id, get_command_responses};
-    use crate::interaction_endpoint::command_handler::Interaction;
+    use crate::discord_client::discord_client::{self, DiscordClient};
+    use crate::limits::limits;
+    use crate::init_commands::init_commands::{get_api_url, get_application_id, MessageData, EPHEMERAL, SUPPRESS_EMBEDS};
+    use serde_json::{json, Value};
+    use crate::interaction_endpoint::command_handler::DeferredCommand;
+    use crate::templates::templates::{render_message, TemplateContext};
+    use crate::variants::variants;
 
-    pub fn handle_responses(receiver: Receiver<Interaction>, threads: u8){
+    // interaction tokens can be used for 15 minutes, leave some room for the last request
+    const TOKEN_LIFETIME: Duration = Duration::from_secs(14 * 60 + 30);
+    const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+    const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+    const FAILURE_MESSAGE: &str = "Sorry, something went wrong whilst responding to this command.";
+
+    /// The outcome of sending a message to discord
+    #[derive(Debug)]
+    enum Delivery {
+        Sent,
+        // network errors and server errors might go away when trying again
+        Failed,
+        // discord refused the message itself, sending it again won't help
+        Rejected
+    }
+
+    /// A message on its way to discord, along with the messages to send after it
+    struct PendingMessage {
+        command: String,
+        // the webhook url of the interaction
+        url: String,
+        message: MessageData,
+        // whether this is the response replacing the deferred one, or a follow-up
+        original: bool,
+        // how long to wait before trying again should sending fail
+        retry_delay: Duration,
+        expires_at: Instant,
+        // the follow-ups still to send and how long to wait before each
+        followups: VecDeque<(Duration, MessageData)>
+    }
+
+    // a message and when it is due
+    type Scheduled = (Instant, PendingMessage);
+
+    /// Runs messages on the threadpool once they are due, so waiting for a delay or a retry never takes up a thread
+    #[derive(Clone)]
+    struct Scheduler {
+        pool: ThreadPool,
+        sender: Sender<Scheduled>
+    }
+
+    impl Scheduler {
+        fn start(pool: ThreadPool) -> Scheduler {
+            let (sender, receiver): (Sender<Scheduled>, Receiver<Scheduled>) = mpsc::channel();
+            let scheduler = Scheduler { pool, sender };
+            let timer = scheduler.clone();
+            thread::spawn(move || {
+                let mut waiting: Vec<Scheduled> = vec![];
+                loop {
+                    let now = Instant::now();
+                    let (due, later): (Vec<Scheduled>, Vec<Scheduled>) = waiting.into_iter().partition(|(at, _)| *at <= now);
+                    waiting = later;
+                    for (_, pending) in due {
+                        let scheduler = timer.clone();
+                        timer.pool.execute(move || attempt(pending, &scheduler));
+                    }
+                    let received = match waiting.iter().map(|(at, _)| *at).min() {
+                        Some(next) => receiver.recv_timeout(next.saturating_duration_since(Instant::now())),
+                        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                    };
+                    match received {
+                        Ok(pending) => waiting.push(pending),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break
+                    }
+                }
+            });
+            scheduler
+        }
+
+        fn schedule(&self, after: Duration, pending: PendingMessage) {
+            let _ = self.sender.send((Instant::now() + after, pending));
+        }
+    }
+
+    pub fn handle_responses(receiver: Receiver<DeferredCommand>, threads: u8){
         let pool = ThreadPool::new(threads as usize);
+        let scheduler = Scheduler::start(pool.clone());
         loop {
             match receiver.recv() {
-                Ok(interaction) => {
+                Ok(DeferredCommand { interaction, variant }) => {
+                    let expires_at = Instant::now() + TOKEN_LIFETIME;
+                    let scheduler = scheduler.clone();
                     pool.execute(move || {
-                        let data = &interaction.data.unwrap();
-                        let name = &data.name;
-                        for response in get_command_responses() {
-                            if response.name.as_str().eq(name.as_str()) {
-                                let client = reqwest::blocking::Client::new();
-                                let body = serde_json::to_string(&response.message).unwrap();
-                                let url = "https://discord.com/api/webhooks/".to_owned() + get_application_id().as_str() + "/" + &interaction.token + "/messages/@original";
-                                let response = client.patch(url)
-                                    .header("Content-Type", "application/json")
-                                    .body(body)
-                                    .send();
-                                match response {
-                                    Ok(resp) => {
-                                        let status = &resp.status();
-                                        if status != &StatusCode::OK {
-                                            println!("There was an error whilst responding to a command! {}", status);
-                                        }
-                                    }
-                                    Err(err) => {
-                                        println!("Failed to respond to command: {:?}", err);
-                                    }
-                                }
-                                break;
-                            }
+                        let data = interaction.data.as_ref().unwrap();
+                        // walk the subcommands to find the one that was invoked
+                        if let Some(response) = data.find_command() {
+                            let context = TemplateContext::from_interaction(&interaction);
+                            let mut message = render_message(variants::message(&response, variant, interaction.locale.as_deref()), &context);
+                            // the deferred response already made the message ephemeral or silent, edits only take this flag
+                            message.flags &= SUPPRESS_EMBEDS;
+                            limits::fit_message(&mut message, response.name.as_str());
+                            let followups = response.followups.iter()
+                                .map(|followup| {
+                                    let mut message = render_message(&followup.message, &context);
+                                    limits::fit_message(&mut message, response.name.as_str());
+                                    (followup.delay, message)
+                                })
+                                .collect();
+                            let pending = PendingMessage {
+                                command: response.name.clone(),
+                                url: get_api_url() + "/webhooks/" + get_application_id().as_str() + "/" + &interaction.token,
+                                message,
+                                original: true,
+                                retry_delay: FIRST_RETRY_DELAY,
+                                expires_at,
+                                followups,
+                            };
+                            attempt(pending, &scheduler);
                         }
                     });
                 }
@@ -46,4 +137,177 @@ pub mod handle_responses {
         }
     }
 
-}
\ No newline at end of file
+    /// Tries to send the message once. A failed message is tried again with an exponential backoff for as long
+    /// as the interaction token is valid, and the next follow-up is scheduled once it is sent
+    fn attempt(mut pending: PendingMessage, scheduler: &Scheduler) {
+        let client = discord_client::shared();
+        let url = pending.url.clone();
+        let delivery = if pending.original {
+            send(client, |client| client.patch(url.clone() + "/messages/@original"), &pending.message)
+        } else {
+            send(client, |client| client.post(url.as_str()), &pending.message)
+        };
+        match delivery {
+            Delivery::Sent => {}
+            Delivery::Failed if Instant::now() + pending.retry_delay < pending.expires_at => {
+                println!("Retrying the response to {} in {:?}", pending.command, pending.retry_delay);
+                let delay = pending.retry_delay;
+                pending.retry_delay = (delay * 2).min(MAX_RETRY_DELAY);
+                scheduler.schedule(delay, pending);
+                return;
+            }
+            Delivery::Failed | Delivery::Rejected => {
+                if let Delivery::Failed = delivery {
+                    println!("Giving up on responding to {}, the interaction token expires before the next retry.", pending.command);
+                }
+                if pending.original {
+                    // let the user know instead of leaving them with a response that never finishes loading
+                    let failure = MessageData { content: FAILURE_MESSAGE.to_string(), flags: EPHEMERAL, ..MessageData::default() };
+                    send(client, |client| client.post(url.as_str()), &failure);
+                    return;
+                }
+            }
+        }
+        if let Some((delay, message)) = pending.followups.pop_front() {
+            let next = PendingMessage { message, original: false, retry_delay: FIRST_RETRY_DELAY, ..pending };
+            scheduler.schedule(delay, next);
+        }
+    }
+
+    fn send<F>(client: &DiscordClient, request: F, message: &MessageData) -> Delivery where F: Fn(&Client) -> RequestBuilder {
+        let body = serde_json::to_string(message).unwrap();
+        let response = client.send(|client| {
+            if message.files.is_empty() {
+                request(client)
+                    .header("Content-Type", "application/json")
+                    .body(body.clone())
+            } else {
+                request(client).multipart(multipart_form(message))
+            }
+        });
+        match response {
+            Ok(resp) => {
+                let status = resp.status();
+                if status.is_success() {
+                    return Delivery::Sent;
+                }
+                println!("There was an error whilst responding to a command! {}", status);
+                if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                    Delivery::Failed
+                } else {
+                    println!("{:#?}", resp.text());
+                    Delivery::Rejected
+                }
+            }
+            Err(err) => {
+                println!("Failed to respond to command: {:?}", err);
+                Delivery::Failed
+            }
+        }
+    }
+
+    /// Builds the multipart body uploading the message's files, files that can't be read are left out
+    fn multipart_form(message: &MessageData) -> Form {
+        let mut payload = serde_json::to_value(message).unwrap();
+        let mut attachments: Vec<Value> = vec![];
+        let mut form = Form::new();
+        for path in &message.files {
+            match fs::read(path) {
+                Ok(bytes) => {
+                    let index = attachments.len();
+                    let filename = Path::new(path).file_name().unwrap_or_default().to_string_lossy().to_string();
+                    attachments.push(json!({"id": index, "filename": filename}));
+                    form = form.part(format!("files[{}]", index), Part::bytes(bytes).file_name(filename));
+                }
+                Err(err) => {
+                    println!("Failed to attach file {}: {:?}", path, err);
+                }
+            }
+        }
+        payload["attachments"] = Value::from(attachments);
+        form.text("payload_json", payload.to_string())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::collections::VecDeque;
+        use std::{env, fs};
+        use std::time::{Duration, Instant};
+        use serde_json::{json, Value};
+        use threadpool::ThreadPool;
+        use crate::discord_client::discord_client::DiscordClient;
+        use crate::init_commands::init_commands::MessageData;
+        use crate::mock_discord::mock_discord::MockDiscord;
+        use super::{attempt, send, Delivery, PendingMessage, Scheduler, FAILURE_MESSAGE, FIRST_RETRY_DELAY};
+
+        fn pending(discord: &MockDiscord, token: &str, expires_in: Duration) -> PendingMessage {
+            PendingMessage {
+                command: "test".to_string(),
+                url: format!("{}/webhooks/1/{}", discord.url, token),
+                message: MessageData { content: "Hello".to_string(), ..MessageData::default() },
+                original: true,
+                retry_delay: FIRST_RETRY_DELAY,
+                expires_at: Instant::now() + expires_in,
+                followups: VecDeque::from(vec![(Duration::ZERO, MessageData { content: "More".to_string(), ..MessageData::default() })]),
+            }
+        }
+
+        #[test]
+        fn retries_without_holding_up_other_responses() {
+            let discord = MockDiscord::start();
+            // a single thread, which the retry must not keep to itself whilst waiting
+            let scheduler = Scheduler::start(ThreadPool::new(1));
+            discord.fail_next(1);
+            attempt(pending(&discord, "first", Duration::from_secs(60)), &scheduler);
+            attempt(pending(&discord, "second", Duration::from_secs(60)), &scheduler);
+
+            discord.wait_for("POST", "/webhooks/1/first").unwrap();
+            let paths: Vec<String> = discord.requests().into_iter().map(|request| request.method + " " + request.path.as_str()).collect();
+            assert_eq!(paths, vec![
+                "PATCH /api/webhooks/1/first/messages/@original",
+                "PATCH /api/webhooks/1/second/messages/@original",
+                "POST /api/webhooks/1/second",
+                "PATCH /api/webhooks/1/first/messages/@original",
+                "POST /api/webhooks/1/first",
+            ]);
+        }
+
+        #[test]
+        fn tells_the_user_once_the_token_is_about_to_expire() {
+            let discord = MockDiscord::start();
+            let scheduler = Scheduler::start(ThreadPool::new(1));
+            discord.fail_next(10);
+            attempt(pending(&discord, "token", Duration::from_secs(2)), &scheduler);
+
+            // the first try and a retry after a second, the next retry would be after the token expired
+            let failure = discord.wait_for("POST", "/webhooks/1/token").unwrap();
+            assert_eq!(failure.json()["content"], FAILURE_MESSAGE);
+            let edits = discord.requests().iter().filter(|request| request.method == "PATCH").count();
+            assert_eq!(edits, 2);
+        }
+
+        #[test]
+        fn uploads_files_as_multipart() {
+            let discord = MockDiscord::start();
+            let path = env::temp_dir().join("selfuserbot-upload.txt");
+            fs::write(&path, "file contents").unwrap();
+            let message = MessageData {
+                content: "See attached".to_string(),
+                files: vec![path.to_str().unwrap().to_string(), "missing.txt".to_string()],
+                ..MessageData::default()
+            };
+            let url = discord.url.clone() + "/webhooks/1/token";
+            assert!(matches!(send(&DiscordClient::new(), |client| client.post(url.as_str()), &message), Delivery::Sent));
+
+            let body = discord.requests()[0].body.clone();
+            // the part holding the message itself, which lists the files that could be read
+            let payload = body.split("name=\"payload_json\"\r\n\r\n").nth(1).unwrap().split("\r\n--").next().unwrap();
+            let payload: Value = serde_json::from_str(payload).unwrap();
+            assert_eq!(payload["content"], "See attached");
+            assert_eq!(payload["attachments"], json!([{"id": 0, "filename": "selfuserbot-upload.txt"}]));
+            assert!(body.contains("name=\"files[0]\"; filename=\"selfuserbot-upload.txt\""), "{}", body);
+            assert!(body.contains("file contents"), "{}", body);
+            assert!(!body.contains("files[1]"), "{}", body);
+        }
+    }
+}
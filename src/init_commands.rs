@@ -1,12 +1,16 @@
 #![allow(dead_code)]
 pub mod init_commands {
-    use std::fs;
+    use std::{fs, process, thread};
+    use std::collections::BTreeMap;
     use std::fs::File;
     use std::ops::Deref;
     use std::sync::{RwLock};
+    use std::time::{Duration, SystemTime};
     use config::Config;
     use lazy_static::lazy_static;
-    use reqwest::StatusCode;
+    use crate::discord_client::discord_client::{self, DiscordClient};
+    use crate::limits::limits;
+    use crate::sync_commands::sync_commands;
     use serde::Serialize;
     use serde_json::{json, Value, from_str};
 
@@ -16,24 +20,76 @@ pub mod init_commands {
     lazy_static! {
         static ref COMMAND_RESPONSES: RwLock<Vec<CommandResponse>> = RwLock::new(vec![]);
     }
+    lazy_static! {
+        static ref API_URL: RwLock<String> = RwLock::new(DEFAULT_API_URL.to_string());
+    }
+    lazy_static! {
+        static ref COMMANDS_FILE: RwLock<String> = RwLock::new("Commands.yml".to_string());
+    }
+    lazy_static! {
+        // guilds from Config.yml that every command is registered to instead of globally
+        static ref GUILD_OVERRIDE: RwLock<Vec<String>> = RwLock::new(vec![]);
+    }
+    lazy_static! {
+        // every guild commands have been registered to, so commands can be removed from guilds that are no longer configured.
+        // This only lives as long as the process, so a guild dropped from the config while the bot
+        // is stopped keeps its commands until they are removed by hand
+        static ref SYNCED_GUILDS: RwLock<Vec<String>> = RwLock::new(vec![]);
+    }
+
+    pub const DEFAULT_API_URL: &str = "https://discord.com/api";
+
+    // message flags, only the user that caused an ephemeral message can see it
+    pub const SUPPRESS_EMBEDS: u64 = 1 << 2;
+    pub const EPHEMERAL: u64 = 1 << 6;
+    pub const SUPPRESS_NOTIFICATIONS: u64 = 1 << 12;
 
     pub fn get_command_responses() -> Vec<CommandResponse> {
         let r = COMMAND_RESPONSES.read().unwrap();
-        return r.clone();
+        r.clone()
     }
 
     pub fn get_application_id() -> String {
         let r = APPLICATION_ID.read().unwrap();
-        return r.clone();
+        r.clone()
+    }
+
+    /// The base url every discord api request is made against, without a trailing slash
+    pub fn get_api_url() -> String {
+        let r = API_URL.read().unwrap();
+        r.clone()
+    }
+
+    pub fn set_api_url(url: &str) {
+        let mut w = API_URL.write().unwrap();
+        *w = url.trim_end_matches('/').to_string();
+    }
+
+    pub fn get_commands_file() -> String {
+        let r = COMMANDS_FILE.read().unwrap();
+        r.clone()
+    }
+
+    pub fn set_commands_file(path: &str) {
+        let mut w = COMMANDS_FILE.write().unwrap();
+        *w = path.to_string();
+    }
+
+    /// Registers every command to these guilds rather than globally, which is useful for a development profile
+    pub fn set_guild_override(guilds: Vec<String>) {
+        let mut w = GUILD_OVERRIDE.write().unwrap();
+        *w = guilds;
     }
 
     #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub enum IntegrationType {
         Guild,
         User
     }
 
     #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub enum InteractionContext {
         Guild,
         BotDm,
@@ -46,6 +102,15 @@ pub mod init_commands {
                 IntegrationType::User => {1}
             }
         }
+
+        /// Maps the name used in Commands.yml to the integration type
+        pub fn from_name(name: &str) -> Option<IntegrationType> {
+            match name {
+                "guild" => Some(IntegrationType::Guild),
+                "user" => Some(IntegrationType::User),
+                _ => None
+            }
+        }
     }
     impl InteractionContext {
         pub fn raw(&self) -> u8 {
@@ -55,265 +120,1160 @@ pub mod init_commands {
                 InteractionContext::PrivateChannel => {2}
             }
         }
+
+        /// Maps the name used in Commands.yml to the interaction context
+        pub fn from_name(name: &str) -> Option<InteractionContext> {
+            match name {
+                "guild" => Some(InteractionContext::Guild),
+                "bot_dm" => Some(InteractionContext::BotDm),
+                "private_channel" => Some(InteractionContext::PrivateChannel),
+                _ => None
+            }
+        }
     }
 
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum CommandType {
+        ChatInput,
+        User,
+        Message
+    }
+    impl CommandType {
+        pub fn raw(&self) -> u8 {
+            match &self {
+                CommandType::ChatInput => {1}
+                CommandType::User => {2}
+                CommandType::Message => {3}
+            }
+        }
 
-    pub fn load_cmds(token: &str){
-        let client = reqwest::blocking::Client::new();
+        /// Maps the name used in Commands.yml to the command type
+        pub fn from_name(name: &str) -> Option<CommandType> {
+            match name {
+                "chat_input" => Some(CommandType::ChatInput),
+                "user" => Some(CommandType::User),
+                "message" => Some(CommandType::Message),
+                _ => None
+            }
+        }
+    }
+
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum CommandOptionType {
+        SubCommand,
+        SubCommandGroup,
+        String,
+        Integer,
+        Boolean,
+        User,
+        Channel,
+        Role,
+        Mentionable,
+        Number,
+        Attachment
+    }
+    impl CommandOptionType {
+        pub fn raw(&self) -> u8 {
+            match &self {
+                CommandOptionType::SubCommand => {1}
+                CommandOptionType::SubCommandGroup => {2}
+                CommandOptionType::String => {3}
+                CommandOptionType::Integer => {4}
+                CommandOptionType::Boolean => {5}
+                CommandOptionType::User => {6}
+                CommandOptionType::Channel => {7}
+                CommandOptionType::Role => {8}
+                CommandOptionType::Mentionable => {9}
+                CommandOptionType::Number => {10}
+                CommandOptionType::Attachment => {11}
+            }
+        }
+
+        /// Maps the name used in Commands.yml to the option type
+        pub fn from_name(name: &str) -> Option<CommandOptionType> {
+            match name {
+                "string" => Some(CommandOptionType::String),
+                "integer" => Some(CommandOptionType::Integer),
+                "boolean" => Some(CommandOptionType::Boolean),
+                "user" => Some(CommandOptionType::User),
+                "channel" => Some(CommandOptionType::Channel),
+                "role" => Some(CommandOptionType::Role),
+                "mentionable" => Some(CommandOptionType::Mentionable),
+                "number" => Some(CommandOptionType::Number),
+                "attachment" => Some(CommandOptionType::Attachment),
+                _ => None
+            }
+        }
+    }
+
+
+    /// Fetches the application id, loads Commands.yml and brings discord in line with it.
+    /// In a dry run the sync plan is only printed
+    pub fn load_cmds(token: &str, dry_run: bool){
+        let client = discord_client::shared();
         let auth_header = "Bot ".to_owned() + token;
         // send a request to discord to get the application id
-        let application_response = client.get("https://discord.com/api/applications/@me")
-            .header("Authorization", &auth_header)
-            .send();
+        let application_response = client.send(|client| client.get(get_api_url() + "/applications/@me")
+            .header("Authorization", &auth_header));
 
         // parse the body
-        match application_response {
-            Ok(resp) => {
-                let text: String = resp.text().unwrap();
-                let json: Value = from_str(text.as_str()).unwrap();
-                let mut w = APPLICATION_ID.write().unwrap();
-                *w = json.get("id").unwrap().as_str().unwrap().to_string();
-                let _ = w.deref();
-            }
-            Err(_) => {}
+        if let Ok(resp) = application_response {
+            let text: String = resp.text().unwrap();
+            let json: Value = from_str(text.as_str()).unwrap();
+            let mut w = APPLICATION_ID.write().unwrap();
+            *w = json.get("id").unwrap().as_str().unwrap().to_string();
+            let _ = w.deref();
         }
         let r = APPLICATION_ID.read().unwrap();
         println!("APPLICATION_ID: {}", r.as_str());
+        let _ = r.deref();
         // get the configurable commands from the config file and stores them in memory
-        get_commands_from_file();
+        // refuse to start with a broken Commands.yml rather than registering half of it
+        let commands = match get_commands_from_file() {
+            Ok(commands) => commands,
+            Err(report) => {
+                println!("{}", report);
+                process::exit(1);
+            }
+        };
+        let mut w = COMMAND_RESPONSES.write().unwrap();
+        *w = commands;
+        let _ = w.deref();
+        drop(w);
+
+        register_commands(client, token, &get_command_responses(), dry_run);
+    }
 
-        // create a vec of CommandData for all the configured commands
+    /// Diffs the configured commands against the ones registered with discord and
+    /// only creates, updates or deletes what changed.
+    /// Global commands and the commands of each guild are synced separately
+    fn register_commands(client: &DiscordClient, token: &str, commands: &[CommandResponse], dry_run: bool) {
+        let auth_header = "Bot ".to_owned() + token;
+        let application_url = get_api_url() + "/applications/" + get_application_id().as_str();
+
+        // a guild override is for trying commands out, possibly on an application that also runs
+        // globally, so the global commands are left alone instead of being deleted
+        let mut success = true;
+        if GUILD_OVERRIDE.read().unwrap().is_empty() {
+            let global: Vec<CommandResponse> = commands.iter().filter(|command| command.guilds.is_empty()).cloned().collect();
+            success = sync_scope(client, &auth_header, application_url.clone() + "/commands", "global", &global, dry_run);
+        } else {
+            println!("Leaving the global commands alone since discord.guilds is set");
+        }
+
+        let mut guilds: Vec<String> = SYNCED_GUILDS.read().unwrap().clone();
+        for command in commands {
+            for guild in &command.guilds {
+                if !guilds.contains(guild) {
+                    guilds.push(guild.clone());
+                }
+            }
+        }
+        for guild in &guilds {
+            let guild_commands: Vec<CommandResponse> = commands.iter().filter(|command| command.guilds.contains(guild)).cloned().collect();
+            let url = application_url.clone() + "/guilds/" + guild.as_str() + "/commands";
+            success &= sync_scope(client, &auth_header, url, format!("guild {}", guild).as_str(), &guild_commands, dry_run);
+        }
+        if dry_run {
+            return;
+        }
+        *SYNCED_GUILDS.write().unwrap() = guilds;
+        if success {
+            println!("Sent all commands to discord!");
+        } else {
+            println!("Discord returned an error upon sending your commands.");
+        }
+    }
+
+    /// Syncs the commands registered at the given commands url, returning whether it succeeded
+    fn sync_scope(client: &DiscordClient, auth_header: &str, url: String, scope: &str, commands: &[CommandResponse], dry_run: bool) -> bool {
+        let registered = match sync_commands::fetch_registered(client, auth_header, url.as_str()) {
+            Ok(registered) => registered,
+            Err(err) => {
+                println!("An error occurred whilst fetching the {} commands from discord! Error: {}", scope, err);
+                return false;
+            }
+        };
+        let local: Vec<Value> = command_data(commands).iter().map(|data| data.to_json()).collect();
+        let plan = sync_commands::plan(local, registered);
+        plan.print(scope);
+        if dry_run || plan.is_empty() {
+            return true;
+        }
+        sync_commands::apply(client, auth_header, url.as_str(), &plan)
+    }
+
+    /// Creates the CommandData discord needs to register each of the configured commands
+    fn command_data(commands: &[CommandResponse]) -> Vec<CommandData> {
         let mut command_data_to_send: Vec<CommandData> = Vec::new();
-        let r = COMMAND_RESPONSES.read().unwrap();
-        for response in r.to_vec() {
+        for response in commands.iter().cloned() {
+            let options = if response.subcommands.is_empty() {
+                response.options
+            } else {
+                response.subcommands.iter().map(|subcommand| subcommand.to_subcommand_option()).collect()
+            };
             command_data_to_send.push(
                 CommandData {
                     name: response.name,
+                    r#type: response.r#type,
                     description: response.description,
-                    integration_types: vec![IntegrationType::User],
-                    contexts: vec![InteractionContext::BotDm, InteractionContext::Guild, InteractionContext::PrivateChannel],
+                    name_localizations: response.name_localizations,
+                    description_localizations: response.description_localizations,
+                    options,
+                    integration_types: response.integration_types,
+                    contexts: response.contexts,
                 }
             );
         }
-        let _ = r.deref();
-        let mut body: String = "[".to_string();
-        for data in command_data_to_send {
-            body.push_str(data.to_body().as_str());
-            body.push(',')
-        }
-        let _ = body.pop();
-        body.push(']');
-        let response = client.put("https://discord.com/api/applications/".to_owned() + get_application_id().as_str() + "/commands")
-            .header("Authorization", &auth_header)
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send();
-        match response {
-            Ok(resp) => {
-                if resp.status() == StatusCode::OK {
-                    println!("Sent all commands to discord!");
-                }else {
-                    println!("Discord returned an error upon sending your commands.");
-                    println!("{:#?}", resp.text());
+        command_data_to_send
+    }
+
+    /// Watches Commands.yml and swaps in the new commands whenever the file changes
+    pub fn watch_commands(token: String) {
+        thread::spawn(move || {
+            let client = discord_client::shared();
+            let mut last_modified = modified_time();
+            loop {
+                thread::sleep(Duration::from_secs(2));
+                let modified = modified_time();
+                if modified == last_modified {
+                    continue;
                 }
+                last_modified = modified;
+                reload_commands(client, token.as_str());
             }
+        });
+    }
+
+    /// Swaps in the commands from Commands.yml, keeping the previous ones when it can't be used.
+    /// Discord is only told about the change when the registration payload differs
+    pub fn reload_commands(client: &DiscordClient, token: &str) {
+        // a missing file is far more likely being replaced than meant to remove every command
+        if fs::metadata(get_commands_file()).is_err() {
+            println!("Commands.yml is missing, keeping the previous commands.");
+            return;
+        }
+        let commands = match read_commands(get_commands_file().as_str()) {
+            Ok(commands) => commands,
             Err(err) => {
-                println!("An error occurred whilst sending commands to discord! Error: {:?}", err);
+                println!("Failed to reload Commands.yml, keeping the previous commands.\n{}", err);
+                return;
             }
+        };
+        if commands.is_empty() && !get_command_responses().is_empty() {
+            println!("Commands.yml has no commands, keeping the previous commands. Restart the bot to remove every command.");
+            return;
+        }
+        let old_registration = registration(&get_command_responses());
+        let new_registration = registration(&commands);
+        let mut w = COMMAND_RESPONSES.write().unwrap();
+        *w = commands;
+        drop(w);
+        println!("Reloaded Commands.yml");
+
+        if old_registration != new_registration {
+            register_commands(client, token, &get_command_responses(), false);
         }
     }
 
-    fn get_commands_from_file() {
-        // create the Commands.yml file if it doesn't already exist
-        if !fs::metadata("Commands.yml").is_ok() {
-            match File::create("Commands.yml") {
-                Ok(_) => {}
+    /// Everything about the commands that discord needs to know about
+    fn registration(commands: &[CommandResponse]) -> Vec<(Vec<String>, String)> {
+        commands.iter()
+            .zip(command_data(commands))
+            .map(|(command, data)| (command.guilds.clone(), data.to_body()))
+            .collect()
+    }
+
+    fn modified_time() -> Option<SystemTime> {
+        fs::metadata(get_commands_file()).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    /// Every problem found whilst reading Commands.yml, each with the path to the value it is about
+    /// (e.g. faq.embeds.2.color). Invalid values are reported and skipped so everything is checked at once
+    #[derive(Default)]
+    struct Validation {
+        errors: Vec<String>
+    }
+
+    impl Validation {
+        fn error(&mut self, path: &str, message: String) {
+            self.errors.push(format!("{}: {}", path, message));
+        }
+
+        fn table(&mut self, path: &str, value: config::Value) -> Vec<(String, config::Value)> {
+            match value.into_table() {
+                Ok(table) => table.into_iter().collect(),
+                Err(_) => {
+                    self.error(path, "expected a table".to_string());
+                    vec![]
+                }
+            }
+        }
+
+        fn array(&mut self, path: &str, value: config::Value) -> Vec<config::Value> {
+            match value.into_array() {
+                Ok(array) => array,
                 Err(_) => {
-                    println!("Unable to create new Commands.yml file. Please check file permissions");
-                    return;
+                    self.error(path, "expected a list".to_string());
+                    vec![]
                 }
             }
         }
 
+        fn string(&mut self, path: &str, value: config::Value) -> Option<String> {
+            match value.into_string() {
+                Ok(string) => Some(string),
+                Err(_) => {
+                    self.error(path, "expected a string".to_string());
+                    None
+                }
+            }
+        }
+
+        fn bool(&mut self, path: &str, value: config::Value) -> Option<bool> {
+            match value.into_bool() {
+                Ok(bool) => Some(bool),
+                Err(_) => {
+                    self.error(path, "expected true or false".to_string());
+                    None
+                }
+            }
+        }
+
+        fn int(&mut self, path: &str, value: config::Value) -> Option<i64> {
+            match value.into_int() {
+                Ok(int) => Some(int),
+                Err(_) => {
+                    self.error(path, "expected a whole number".to_string());
+                    None
+                }
+            }
+        }
+
+        fn float(&mut self, path: &str, value: config::Value) -> Option<f64> {
+            match value.into_float() {
+                Ok(float) => Some(float),
+                Err(_) => {
+                    self.error(path, "expected a number".to_string());
+                    None
+                }
+            }
+        }
+
+        /// A whole number that has to fit the target type, such as a u16 length
+        fn uint<T: TryFrom<u64>>(&mut self, path: &str, value: config::Value) -> Option<T> {
+            let uint = value.into_uint().ok().and_then(|uint| T::try_from(uint).ok());
+            if uint.is_none() {
+                self.error(path, "expected a positive whole number within range".to_string());
+            }
+            uint
+        }
+
+        /// Reports a key of the table at `path` that isn't used, which is most likely a typo
+        fn unknown_key(&mut self, path: &str, key: &str) {
+            self.error(path, format!("unknown key '{}'", key));
+        }
+
+        fn strings(&mut self, path: &str, value: config::Value) -> Vec<String> {
+            self.array(path, value)
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, value)| self.string(join(path, index.to_string().as_str()).as_str(), value))
+                .collect()
+        }
+
+        /// Every problem as one report
+        fn report(&self) -> String {
+            let mut report = format!("Commands.yml has {} problem(s):", self.errors.len());
+            for error in &self.errors {
+                report.push_str("\n  ");
+                report.push_str(error.as_str());
+            }
+            report
+        }
+    }
+
+    /// The path to a value inside the one at `path`
+    fn join(path: &str, key: &str) -> String {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    }
+
+    fn get_commands_from_file() -> Result<Vec<CommandResponse>, String> {
+        let path = get_commands_file();
+        // create the Commands.yml file if it doesn't already exist
+        if fs::metadata(&path).is_err() && File::create(&path).is_err() {
+            return Err("Unable to create new Commands.yml file. Please check file permissions".to_string());
+        }
+        read_commands(path.as_str())
+    }
+
+    /// Reads the commands file, returning a report of every problem with it if it is invalid
+    pub fn read_commands(path: &str) -> Result<Vec<CommandResponse>, String> {
+
         // load the config file from disk
         let commands: Config = Config::builder()
-            .add_source(config::File::with_name("Commands.yml"))
+            .add_source(config::File::with_name(path))
             .build()
-            .expect("Failed to read Commands.yml. Please check file permissions.");
-        let values = commands.cache.into_table().expect("Error parsing Commands.yml");
+            .map_err(|err| format!("Failed to read Commands.yml. {}", err))?;
+        let mut values = commands.cache.into_table().map_err(|err| format!("Error parsing Commands.yml. {}", err))?;
+        let mut validation = Validation::default();
+
+        // the defaults section applies to every command that doesn't override it
+        let mut defaults = CommandDefaults {
+            integration_types: vec![IntegrationType::User],
+            contexts: vec![InteractionContext::BotDm, InteractionContext::Guild, InteractionContext::PrivateChannel],
+            guilds: vec![],
+        };
+        if let Some(value) = values.shift_remove("defaults") {
+            for (key, value) in validation.table("defaults", value) {
+                let path = join("defaults", key.as_str());
+                match key.as_str() {
+                    "integration_types" => { defaults.integration_types = parse_integration_types(&path, value, &mut validation) }
+                    "contexts" => { defaults.contexts = parse_contexts(&path, value, &mut validation) }
+                    "guilds" => { defaults.guilds = validation.strings(&path, value) }
+                    &_ => validation.unknown_key("defaults", key.as_str())
+                }
+            }
+        }
 
         let mut new_commands: Vec<CommandResponse> = Vec::new();
         // iterate over each command
         for (name, value) in values {
-            // initialize defaults
-            let mut description: String = "".to_string();
-            let mut content: String = "".to_string();
-            let mut embeds: Vec<MessageEmbed> = Vec::new();
-            let mut components: Vec<ActionRow> = Vec::new();
+            let path = name.clone();
+            new_commands.push(parse_command(name, &path, value, &defaults, 0, &mut validation));
+        }
+        // catch what discord would refuse before registering or sending anything
+        for command in &new_commands {
+            validation.errors.extend(limits::check_command(command, command.name.as_str()));
+        }
+        if !validation.errors.is_empty() {
+            return Err(validation.report());
+        }
+        Ok(new_commands)
+    }
+
+    /// Parses a command, or a subcommand group or subcommand when depth is 1 or 2
+    fn parse_command(name: String, path: &str, value: config::Value, defaults: &CommandDefaults, depth: u8, validation: &mut Validation) -> CommandResponse {
+        // initialize defaults
+        let mut description: String = "".to_string();
+        let mut command_type: CommandType = CommandType::ChatInput;
+        let mut message = MessageData {
+            content: "".to_string(),
+            embeds: Vec::new(),
+            components: Vec::new(),
+            flags: 0,
+            files: Vec::new(),
+        };
+        let mut name_localizations: BTreeMap<String, String> = BTreeMap::new();
+        let mut description_localizations: BTreeMap<String, String> = BTreeMap::new();
+        let mut locale_messages: Option<config::Value> = None;
+        let mut options: Vec<CommandOption> = Vec::new();
+        let mut integration_types: Vec<IntegrationType> = defaults.integration_types.clone();
+        let mut contexts: Vec<InteractionContext> = defaults.contexts.clone();
+        let mut guilds: Vec<String> = defaults.guilds.clone();
+        let mut subcommands: Vec<CommandResponse> = Vec::new();
+        let mut modal: Option<Modal> = None;
+        let mut defer: bool = false;
+        let mut sequence: Option<config::Value> = None;
+        let mut variant_values: Option<config::Value> = None;
+        let mut no_repeat: bool = false;
+
+        // collect values
+        let command_path = path;
+        for (key, value) in validation.table(path, value) {
+            let path = join(path, key.as_str());
+            let path = path.as_str();
+            // handle finding the description
+            if key == "description"{
+                description = validation.string(path, value).unwrap_or_default();
+                // handle finding whether this is a slash command or a context menu command
+            } else if key == "type" {
+                if let Some(type_name) = validation.string(path, value) {
+                    match CommandType::from_name(type_name.as_str()) {
+                        Some(parsed) => { command_type = parsed }
+                        None => validation.error(path, format!("unknown command type '{}', expected chat_input, user or message", type_name))
+                    }
+                }
+                // handle finding the content, embeds and buttons
+            } else if MESSAGE_KEYS.contains(&key.as_str()) {
+                parse_message_value(key.as_str(), path, value, &mut message, validation);
+            } else if key == "options" {
+                options = parse_options(path, value, validation);
+            } else if key == "integration_types" {
+                integration_types = parse_integration_types(path, value, validation);
+            } else if key == "contexts" {
+                contexts = parse_contexts(path, value, validation);
+            } else if key == "guilds" {
+                guilds = validation.strings(path, value);
+            } else if key == "name_localizations" {
+                name_localizations = parse_localizations(path, value, validation);
+            } else if key == "description_localizations" {
+                description_localizations = parse_localizations(path, value, validation);
+            } else if key == "locales" {
+                locale_messages = Some(value);
+            } else if key == "modal" {
+                modal = Some(parse_modal(path, value, validation));
+            } else if key == "defer" {
+                defer = validation.bool(path, value).unwrap_or(false);
+            } else if key == "messages" {
+                sequence = Some(value);
+            } else if key == "variants" {
+                variant_values = Some(value);
+            } else if key == "no_repeat" {
+                no_repeat = validation.bool(path, value).unwrap_or(false);
+            } else if key == "subcommands" {
+                if depth >= 2 {
+                    validation.error(path, "a subcommand can not have subcommands of its own".to_string());
+                    continue;
+                }
+                for (name, value) in validation.table(path, value) {
+                    let path = join(path, name.as_str());
+                    subcommands.push(parse_command(name, &path, value, defaults, depth + 1, validation));
+                }
+            } else {
+                validation.unknown_key(command_path, key.as_str());
+            }
+        }
+        // discord only takes options or subcommands, the options of a group would never be registered
+        if !subcommands.is_empty() && !options.is_empty() {
+            validation.error(&join(command_path, "options"), "a command with subcommands can't have options, give them to its subcommands instead".to_string());
+        }
+        // the first message of a sequence edits the original response, the rest are sent as follow-ups
+        let mut followups: Vec<FollowUp> = Vec::new();
+        if let Some(sequence) = sequence {
+            let sequence_path = join(path, "messages");
+            for (index, (key, value)) in validation.table(&sequence_path, sequence).into_iter().enumerate() {
+                let entry_path = join(&sequence_path, key.as_str());
+                let mut delay = Duration::ZERO;
+                let mut followup_message = MessageData::default();
+                let target = if index == 0 { &mut message } else { &mut followup_message };
+                for (key, value) in validation.table(&entry_path, value) {
+                    let path = join(&entry_path, key.as_str());
+                    if key == "delay" {
+                        delay = validation.float(&path, value).map(|delay| Duration::from_secs_f64(delay.max(0.0))).unwrap_or_default();
+                    } else if MESSAGE_KEYS.contains(&key.as_str()) {
+                        parse_message_value(key.as_str(), &path, value, target, validation);
+                    } else {
+                        validation.unknown_key(&entry_path, key.as_str());
+                    }
+                }
+                if index > 0 {
+                    followups.push(FollowUp { delay, message: followup_message });
+                }
+            }
+        }
+        // each variant starts from the default message and replaces what it changes
+        let mut variants: Vec<Variant> = Vec::new();
+        if let Some(variant_values) = variant_values {
+            let variants_path = join(path, "variants");
+            for (key, value) in validation.table(&variants_path, variant_values) {
+                let entry_path = join(&variants_path, key.as_str());
+                let mut variant = Variant { weight: 1, message: message.clone(), locales: BTreeMap::new() };
+                let mut variant_locales: Option<config::Value> = None;
+                for (key, value) in validation.table(&entry_path, value) {
+                    let path = join(&entry_path, key.as_str());
+                    if key == "weight" {
+                        variant.weight = validation.uint(&path, value).unwrap_or(1);
+                    } else if key == "locales" {
+                        variant_locales = Some(value);
+                    } else if MESSAGE_KEYS.contains(&key.as_str()) {
+                        parse_message_value(key.as_str(), &path, value, &mut variant.message, validation);
+                    } else {
+                        validation.unknown_key(&entry_path, key.as_str());
+                    }
+                }
+                // a variant replaces the command's message, so it is translated on its own
+                if let Some(variant_locales) = variant_locales {
+                    variant.locales = parse_locales(&join(&entry_path, "locales"), variant_locales, &variant.message, validation);
+                }
+                variants.push(variant);
+            }
+        }
+        let locales = match locale_messages {
+            Some(value) => parse_locales(&join(path, "locales"), value, &message, validation),
+            None => BTreeMap::new()
+        };
+        let guild_override = GUILD_OVERRIDE.read().unwrap();
+        if !guild_override.is_empty() {
+            guilds = guild_override.clone();
+        }
+        drop(guild_override);
+
+        CommandResponse {
+            name,
+            r#type: command_type,
+            description,
+            name_localizations,
+            description_localizations,
+            options,
+            integration_types,
+            contexts,
+            guilds,
+            message,
+            locales,
+            subcommands,
+            modal,
+            defer,
+            followups,
+            variants,
+            no_repeat,
+        }
+    }
+
+    /// Parses messages keyed by discord locale, each starting from the given message and replacing what it translates
+    fn parse_locales(path: &str, value: config::Value, message: &MessageData, validation: &mut Validation) -> BTreeMap<String, MessageData> {
+        let mut locales: BTreeMap<String, MessageData> = BTreeMap::new();
+        for (locale, value) in validation.table(path, value) {
+            let locale_path = join(path, locale.as_str());
+            let mut localized = message.clone();
+            for (key, value) in validation.table(&locale_path, value) {
+                if MESSAGE_KEYS.contains(&key.as_str()) {
+                    parse_message_value(key.as_str(), &join(&locale_path, key.as_str()), value, &mut localized, validation);
+                } else {
+                    validation.unknown_key(&locale_path, key.as_str());
+                }
+            }
+            locales.insert(locale, localized);
+        }
+        locales
+    }
+
+    // the keys of a command that make up the message it responds with
+    const MESSAGE_KEYS: [&str; 8] = ["content", "embeds", "buttons", "selects", "ephemeral", "suppress_embeds", "silent", "files"];
+
+    fn parse_message_value(key: &str, path: &str, value: config::Value, message: &mut MessageData, validation: &mut Validation) {
+        match key {
+            "content" => { message.content = validation.string(path, value).unwrap_or_default() }
+            "embeds" => { message.embeds = parse_embeds(path, value, validation) }
+            "buttons" => {
+                // the buttons share the first row, each select menu comes after in a row of its own
+                message.components.retain(|row| !row.has_buttons());
+                message.components.insert(0, parse_buttons(path, value, validation));
+            }
+            "selects" => {
+                message.components.retain(|row| row.has_buttons());
+                message.components.extend(parse_selects(path, value, validation));
+            }
+            "files" => { message.files = validation.strings(path, value) }
+            "ephemeral" | "suppress_embeds" | "silent" => {
+                let flag = match key {
+                    "ephemeral" => EPHEMERAL,
+                    "suppress_embeds" => SUPPRESS_EMBEDS,
+                    _ => SUPPRESS_NOTIFICATIONS
+                };
+                match validation.bool(path, value) {
+                    Some(true) => { message.flags |= flag }
+                    Some(false) => { message.flags &= !flag }
+                    None => {}
+                }
+            }
+            &_ => {}
+        }
+    }
+
+    fn parse_embeds(path: &str, value: config::Value, validation: &mut Validation) -> Vec<MessageEmbed> {
+        let mut embeds: Vec<MessageEmbed> = Vec::new();
+        // iterate over each embed
+        for (key, value) in validation.table(path, value) {
+            let embed_path = join(path, key.as_str());
+            let mut title: Option<String> = None;
+            let mut description: Option<String> = None;
+            let mut url: Option<String> = None;
+            let mut color: Option<u32> = None;
+            let mut footer: Option<EmbedFooter> = None;
+            let mut image: Option<EmbedImage> = None;
+            let mut thumbnail: Option<EmbedThumbnail> = None;
+            let mut video: Option<EmbedVideo> = None;
+            let mut author: Option<EmbedAuthor> = None;
+            let mut fields: Option<Vec<EmbedField>> = None;
 
             // collect values
-            for (key, value) in value.into_table().unwrap() {
-                // handle finding the description
-                if key == "description"{
-                    description = value.into_string().unwrap_or("".to_string());
-                    // handle finding the content
-                } else if key == "content" {
-                    // collect value
-                    content = value.into_string().unwrap_or("".to_string());
-                    // handle finding the embeds
-                } else if key == "embeds" {
-                    // iterate over each embed
-                    for (_, value) in value.into_table().unwrap() {
-                        let mut title: Option<String> = None;
-                        let mut description: Option<String> = None;
+            for (id, value) in validation.table(&embed_path, value) {
+                let path = join(&embed_path, id.as_str());
+                let path = path.as_str();
+                match id.as_str() {
+                    "title" => { title = validation.string(path, value) }
+                    "description" => { description = validation.string(path, value) }
+                    "url" => { url = validation.string(path, value) }
+                    "color" => {
+                        if let Some(hex) = validation.string(path, value) {
+                            match u32::from_str_radix(hex.as_str(), 16) {
+                                Ok(parsed) => { color = Some(parsed) }
+                                Err(_) => validation.error(path, format!("invalid hex \"{}\"", hex))
+                            }
+                        }
+                    }
+                    "footer" => {
+                        let mut text: String = "".to_string();
+                        let mut icon_url: Option<String> = None;
+                        for (id, value) in validation.table(path, value) {
+                            let path = join(path, id.as_str());
+                            match id.as_str() {
+                                "text" => { text = validation.string(&path, value).unwrap_or_default() }
+                                "icon_url" => { icon_url = validation.string(&path, value) }
+                                &_ => validation.unknown_key(&join(&embed_path, "footer"), id.as_str())
+                            }
+                        }
+                        footer = Some(EmbedFooter { text, icon_url })
+                    }
+                    "image" => {
+                        for (id, value) in validation.table(path, value) {
+                            if id == "url" {
+                                image = validation.string(&join(path, "url"), value).map(|url| EmbedImage { url })
+                            } else {
+                                validation.unknown_key(path, id.as_str());
+                            }
+                        }
+                    }
+                    "thumbnail" => {
+                        for (id, value) in validation.table(path, value) {
+                            if id == "url" {
+                                thumbnail = validation.string(&join(path, "url"), value).map(|url| EmbedThumbnail { url })
+                            } else {
+                                validation.unknown_key(path, id.as_str());
+                            }
+                        }
+                    }
+                    "video" => {
+                        for (id, value) in validation.table(path, value) {
+                            if id == "url" {
+                                video = validation.string(&join(path, "url"), value).map(|url| EmbedVideo { url })
+                            } else {
+                                validation.unknown_key(path, id.as_str());
+                            }
+                        }
+                    }
+                    "author" => {
+                        let mut name: String = String::from("");
                         let mut url: Option<String> = None;
-                        let mut color: Option<u32> = None;
-                        let mut footer: Option<EmbedFooter> = None;
-                        let mut image: Option<EmbedImage> = None;
-                        let mut thumbnail: Option<EmbedThumbnail> = None;
-                        let mut video: Option<EmbedVideo> = None;
-                        let mut author: Option<EmbedAuthor> = None;
-                        let mut fields: Option<Vec<EmbedField>> = None;
-
-                        // collect values
-                        for (id, value) in value.into_table().unwrap() {
+                        let mut icon_url: Option<String> = None;
+                        for (id, value) in validation.table(path, value) {
+                            let path = join(path, id.as_str());
                             match id.as_str() {
-                                "title" => { title = Some(value.into_string().unwrap()) }
-                                "description" => { description = Some(value.into_string().unwrap()) }
-                                "url" => { url = Some(value.into_string().unwrap()) }
-                                "color" => { color = Some(u32::from_str_radix(value.into_string().unwrap().as_str(), 16).unwrap()) }
-                                "footer" => {
-                                    for (id, value) in value.into_table().unwrap() {
-                                        let mut text: String = "".to_string();
-                                        let mut icon_url: Option<String> = None;
-                                        match id.as_str() {
-                                            "text" => { text = value.into_string().unwrap() }
-                                            "icon_url" => { icon_url = Some(value.into_string().unwrap()) }
-                                            &_ => {}
-                                        }
-                                        footer = Some(EmbedFooter { text, icon_url })
-                                    }
-                                }
-                                "image" => {
-                                    for (id, value) in value.into_table().unwrap() {
-                                        if id == "url" {
-                                            image = Some(EmbedImage { url: value.into_string().unwrap() })
-                                        }
-                                    }
-                                }
-                                "thumbnail" => {
-                                    for (id, value) in value.into_table().unwrap() {
-                                        if id == "url" {
-                                            thumbnail = Some(EmbedThumbnail { url: value.into_string().unwrap() })
-                                        }
-                                    }
-                                }
-                                "video" => {
-                                    for (id, value) in value.into_table().unwrap() {
-                                        if id == "url" {
-                                            video = Some(EmbedVideo { url: value.into_string().unwrap() })
-                                        }
-                                    }
+                                "name" => { name = validation.string(&path, value).unwrap_or_default() }
+                                "url" => { url = validation.string(&path, value) }
+                                "icon_url" => { icon_url = validation.string(&path, value) }
+                                &_ => validation.unknown_key(&join(&embed_path, "author"), id.as_str())
+                            }
+                        }
+                        author = Some(EmbedAuthor { name, url, icon_url });
+                    }
+                    "fields" => {
+                        // iterate over each field
+                        let mut new_fields: Vec<EmbedField> = vec![];
+                        for (key, value) in validation.table(path, value) {
+                            let field_path = join(path, key.as_str());
+                            let mut name: String = String::from("");
+                            let mut valuee: String = String::from("");
+                            let mut inline = false;
+                            for (id, value) in validation.table(&field_path, value) {
+                                let path = join(&field_path, id.as_str());
+                                match id.as_str() {
+                                    "name" => { name = validation.string(&path, value).unwrap_or_default() }
+                                    "value" => { valuee = validation.string(&path, value).unwrap_or_default() }
+                                    "inline" => { inline = validation.bool(&path, value).unwrap_or(false) }
+                                    &_ => validation.unknown_key(&field_path, id.as_str())
                                 }
-                                "author" => {
-                                    let mut name: String = String::from("");
-                                    let mut url: Option<String> = None;
-                                    let mut icon_url: Option<String> = None;
-                                    for (id, value) in value.into_table().unwrap() {
-                                        match id.as_str() {
-                                            "name" => { name = value.into_string().unwrap() }
-                                            "url" => { url = Some(value.into_string().unwrap()) }
-                                            "icon_url" => { icon_url = Some(value.into_string().unwrap()) }
-                                            &_ => {}
-                                        }
-                                    }
-                                    author = Some(EmbedAuthor { name, url, icon_url });
+                            }
+                            new_fields.push(EmbedField { name, value: valuee, inline })
+                        }
+                        fields = Some(new_fields);
+                    }
+                    &_ => validation.unknown_key(&embed_path, id.as_str())
+                }
+            }
+            embeds.push(MessageEmbed {
+                title: title.clone(),
+                description: description.clone(),
+                url: url.clone(),
+                color,
+                footer: footer.clone(),
+                image: image.clone(),
+                thumbnail: thumbnail.clone(),
+                video: video.clone(),
+                author: author.clone(),
+                fields: fields.clone(),
+            })
+        }
+        embeds
+    }
+
+    fn parse_buttons(path: &str, value: config::Value, validation: &mut Validation) -> ActionRow {
+        let mut action_row_components: Vec<Component> = vec![];
+        // iterate over each button
+        for (key, value) in validation.table(path, value) {
+            let button_path = join(path, key.as_str());
+            let mut label: String = String::from("");
+            let mut style: u8 = 1;
+            let mut url: Option<String> = None;
+            let mut custom_id: Option<String> = None;
+            let mut response: Option<ComponentResponse> = None;
+            for (id, value) in validation.table(&button_path, value) {
+                let path = join(&button_path, id.as_str());
+                let path = path.as_str();
+                match id.as_str() {
+                    "label" => { label = validation.string(path, value).unwrap_or_default() }
+                    "url" => { url = validation.string(path, value) }
+                    "custom_id" => { custom_id = validation.string(path, value) }
+                    "style" => {
+                        style = match validation.string(path, value).as_deref() {
+                            Some("primary") => 1,
+                            Some("secondary") => 2,
+                            Some("success") => 3,
+                            Some("danger") => 4,
+                            Some("link") => 5,
+                            Some(style) => {
+                                validation.error(path, format!("unknown button style '{}', expected primary, secondary, success, danger or link", style));
+                                1
+                            }
+                            None => 1
+                        }
+                    }
+                    "response" => { response = Some(parse_component_response(path, value, validation)) }
+                    &_ => validation.unknown_key(&button_path, id.as_str())
+                }
+            }
+            // buttons with a url are always link buttons
+            if url.is_some() {
+                style = 5;
+            }
+            action_row_components.push(Component::Button(ButtonComponent {
+                r#type: 2,
+                style,
+                label,
+                url,
+                custom_id,
+                response,
+            }))
+        }
+        ActionRow { r#type: 1, components: action_row_components }
+    }
+
+    fn parse_selects(path: &str, value: config::Value, validation: &mut Validation) -> Vec<ActionRow> {
+        let mut rows: Vec<ActionRow> = vec![];
+        // iterate over each select menu
+        for (key, value) in validation.table(path, value) {
+            let select_path = join(path, key.as_str());
+            let mut select = SelectComponent {
+                r#type: 3,
+                custom_id: String::from(""),
+                placeholder: None,
+                min_values: None,
+                max_values: None,
+                options: vec![],
+                response: None,
+            };
+            for (id, value) in validation.table(&select_path, value) {
+                let path = join(&select_path, id.as_str());
+                let path = path.as_str();
+                match id.as_str() {
+                    "type" => {
+                        select.r#type = match validation.string(path, value).as_deref() {
+                            Some("string") => 3,
+                            Some("user") => 5,
+                            Some("role") => 6,
+                            Some("mentionable") => 7,
+                            Some("channel") => 8,
+                            Some(select_type) => {
+                                validation.error(path, format!("unknown select type '{}', expected string, user, role, mentionable or channel", select_type));
+                                3
+                            }
+                            None => 3
+                        }
+                    }
+                    "custom_id" => { select.custom_id = validation.string(path, value).unwrap_or_default() }
+                    "placeholder" => { select.placeholder = validation.string(path, value) }
+                    "min_values" => { select.min_values = validation.uint(path, value) }
+                    "max_values" => { select.max_values = validation.uint(path, value) }
+                    "response" => { select.response = Some(parse_component_response(path, value, validation)) }
+                    "options" => {
+                        // iterate over each option of a string select
+                        for (key, value) in validation.table(path, value) {
+                            let option_path = join(path, key.as_str());
+                            let mut option = SelectOption {
+                                label: String::from(""),
+                                value: String::from(""),
+                                description: None,
+                                response: None,
+                            };
+                            for (id, value) in validation.table(&option_path, value) {
+                                let path = join(&option_path, id.as_str());
+                                match id.as_str() {
+                                    "label" => { option.label = validation.string(&path, value).unwrap_or_default() }
+                                    "value" => { option.value = validation.string(&path, value).unwrap_or_default() }
+                                    "description" => { option.description = validation.string(&path, value) }
+                                    "response" => { option.response = Some(parse_component_response(&path, value, validation)) }
+                                    &_ => validation.unknown_key(&option_path, id.as_str())
                                 }
-                                "fields" => {
-                                    // iterate over each field
-                                    let mut new_fields: Vec<EmbedField> = vec![];
-                                    for (_, value) in value.into_table().unwrap() {
-                                        let mut name: String = String::from("");
-                                        let mut valuee: String = String::from("");
-                                        let mut inline = false;
-                                        for (id, value) in value.into_table().unwrap() {
-                                            match id.as_str() {
-                                                "name" => { name = value.into_string().unwrap() }
-                                                "value" => { valuee = value.into_string().unwrap() }
-                                                "inline" => { inline = value.into_bool().unwrap() }
-                                                &_ => {}
-                                            }
+                            }
+                            select.options.push(option);
+                        }
+                    }
+                    &_ => validation.unknown_key(&select_path, id.as_str())
+                }
+            }
+            rows.push(ActionRow { r#type: 1, components: vec![Component::Select(select)] });
+        }
+        rows
+    }
+
+    /// Parses what happens when a component is used
+    fn parse_component_response(path: &str, value: config::Value, validation: &mut Validation) -> ComponentResponse {
+        let mut action = ComponentAction::Message { ephemeral: true };
+        let mut ephemeral = true;
+        let mut message = MessageData {
+            content: "".to_string(),
+            embeds: Vec::new(),
+            components: Vec::new(),
+            flags: 0,
+            files: Vec::new(),
+        };
+        let response_path = path;
+        for (key, value) in validation.table(path, value) {
+            let path = join(path, key.as_str());
+            if key == "type" {
+                action = match validation.string(&path, value).as_deref() {
+                    Some("message") => ComponentAction::Message { ephemeral: true },
+                    Some("update") => ComponentAction::Update,
+                    Some("defer_update") => ComponentAction::DeferUpdate,
+                    Some(action) => {
+                        validation.error(&path, format!("unknown component response type '{}', expected message, update or defer_update", action));
+                        continue;
+                    }
+                    None => continue
+                };
+            } else if key == "ephemeral" {
+                ephemeral = validation.bool(&path, value).unwrap_or(true);
+            } else if key == "files" {
+                // components are answered straight away with a json body, which can't carry files
+                validation.error(&path, "files can't be sent in response to a button or select menu".to_string());
+            } else if MESSAGE_KEYS.contains(&key.as_str()) {
+                parse_message_value(key.as_str(), &path, value, &mut message, validation);
+            } else {
+                validation.unknown_key(response_path, key.as_str());
+            }
+        }
+        if let ComponentAction::Message { .. } = action {
+            action = ComponentAction::Message { ephemeral };
+        }
+        ComponentResponse { action, message }
+    }
+
+    fn parse_modal(path: &str, value: config::Value, validation: &mut Validation) -> Modal {
+        let mut title: String = String::from("");
+        let mut inputs: Vec<TextInput> = vec![];
+        let modal_path = path;
+        for (id, value) in validation.table(path, value) {
+            let path = join(path, id.as_str());
+            let path = path.as_str();
+            match id.as_str() {
+                "title" => { title = validation.string(path, value).unwrap_or_default() }
+                "inputs" => {
+                    // iterate over each text input
+                    for (key, value) in validation.table(path, value) {
+                        let input_path = join(path, key.as_str());
+                        let mut input = TextInput {
+                            r#type: 4,
+                            custom_id: String::from(""),
+                            label: String::from(""),
+                            style: 1,
+                            required: true,
+                            min_length: None,
+                            max_length: None,
+                            placeholder: None,
+                            value: None,
+                        };
+                        for (id, value) in validation.table(&input_path, value) {
+                            let path = join(&input_path, id.as_str());
+                            let path = path.as_str();
+                            match id.as_str() {
+                                "custom_id" => { input.custom_id = validation.string(path, value).unwrap_or_default() }
+                                "label" => { input.label = validation.string(path, value).unwrap_or_default() }
+                                "style" => {
+                                    input.style = match validation.string(path, value).as_deref() {
+                                        Some("short") => 1,
+                                        Some("paragraph") => 2,
+                                        Some(style) => {
+                                            validation.error(path, format!("unknown text input style '{}', expected short or paragraph", style));
+                                            1
                                         }
-                                        new_fields.push(EmbedField { name, value: valuee, inline })
+                                        None => 1
                                     }
-                                    fields = Some(new_fields);
                                 }
-                                &_ => {}
+                                "required" => { input.required = validation.bool(path, value).unwrap_or(true) }
+                                "min_length" => { input.min_length = validation.uint(path, value) }
+                                "max_length" => { input.max_length = validation.uint(path, value) }
+                                "placeholder" => { input.placeholder = validation.string(path, value) }
+                                "value" => { input.value = validation.string(path, value) }
+                                &_ => validation.unknown_key(&input_path, id.as_str())
                             }
                         }
-                        embeds.push(MessageEmbed {
-                            title: title.clone(),
-                            description: description.clone(),
-                            url: url.clone(),
-                            color: color.clone(),
-                            footer: footer.clone(),
-                            image: image.clone(),
-                            thumbnail: thumbnail.clone(),
-                            video: video.clone(),
-                            author: author.clone(),
-                            fields: fields.clone(),
-                        })
+                        inputs.push(input);
                     }
-                } else if key == "buttons" {
-                    let mut action_row_components: Vec<UrlButtonComponent> = vec![];
-                    // iterate over each button
-                    for (_, value) in value.into_table().unwrap() {
-                        let mut label: String = String::from("");
-                        let mut url: String = String::from("");
-                        for (id, value) in value.into_table().unwrap() {
-                            if id == "label" {
-                                label = value.into_string().unwrap();
-                            } else if id == "url" {
-                                url = value.into_string().unwrap();
+                }
+                &_ => validation.unknown_key(modal_path, id.as_str())
+            }
+        }
+        Modal { title, inputs }
+    }
+
+    fn parse_localizations(path: &str, value: config::Value, validation: &mut Validation) -> BTreeMap<String, String> {
+        validation.table(path, value)
+            .into_iter()
+            .filter_map(|(locale, value)| {
+                let localized = validation.string(&join(path, locale.as_str()), value)?;
+                Some((locale, localized))
+            })
+            .collect()
+    }
+
+    /// The settings from the defaults section of Commands.yml
+    struct CommandDefaults {
+        integration_types: Vec<IntegrationType>,
+        contexts: Vec<InteractionContext>,
+        guilds: Vec<String>
+    }
+
+    fn parse_integration_types(path: &str, value: config::Value, validation: &mut Validation) -> Vec<IntegrationType> {
+        validation.strings(path, value)
+            .into_iter()
+            .filter_map(|name| {
+                let integration_type = IntegrationType::from_name(name.as_str());
+                if integration_type.is_none() {
+                    validation.error(path, format!("unknown integration type '{}', expected guild or user", name));
+                }
+                integration_type
+            })
+            .collect()
+    }
+
+    fn parse_contexts(path: &str, value: config::Value, validation: &mut Validation) -> Vec<InteractionContext> {
+        validation.strings(path, value)
+            .into_iter()
+            .filter_map(|name| {
+                let context = InteractionContext::from_name(name.as_str());
+                if context.is_none() {
+                    validation.error(path, format!("unknown context '{}', expected guild, bot_dm or private_channel", name));
+                }
+                context
+            })
+            .collect()
+    }
+
+    fn parse_options(path: &str, value: config::Value, validation: &mut Validation) -> Vec<CommandOption> {
+        let mut options: Vec<CommandOption> = vec![];
+        // iterate over each option, the key being the option name
+        for (name, value) in validation.table(path, value) {
+            let option_path = join(path, name.as_str());
+            let mut description: String = String::from("");
+            let mut option_type: CommandOptionType = CommandOptionType::String;
+            let mut required = false;
+            let mut name_localizations: BTreeMap<String, String> = BTreeMap::new();
+            let mut description_localizations: BTreeMap<String, String> = BTreeMap::new();
+            let mut min: Option<config::Value> = None;
+            let mut max: Option<config::Value> = None;
+            let mut choices: Vec<(String, config::Value)> = vec![];
+            let mut autocomplete: Vec<String> = vec![];
+            let mut autocomplete_file: Option<String> = None;
+            for (id, value) in validation.table(&option_path, value) {
+                let path = join(&option_path, id.as_str());
+                let path = path.as_str();
+                match id.as_str() {
+                    "description" => { description = validation.string(path, value).unwrap_or_default() }
+                    "type" => {
+                        if let Some(type_name) = validation.string(path, value) {
+                            match CommandOptionType::from_name(type_name.as_str()) {
+                                Some(parsed) => { option_type = parsed }
+                                None => validation.error(path, format!("unknown option type '{}'", type_name))
                             }
                         }
-                        action_row_components.push(UrlButtonComponent {
-                            r#type: 2,
-                            style: 5,
-                            label,
-                            url,
-                        })
                     }
-                    components.push(ActionRow { r#type: 1, components: action_row_components });
+                    "required" => { required = validation.bool(path, value).unwrap_or(false) }
+                    "name_localizations" => { name_localizations = parse_localizations(path, value, validation) }
+                    "description_localizations" => { description_localizations = parse_localizations(path, value, validation) }
+                    "min" => { min = Some(value) }
+                    "max" => { max = Some(value) }
+                    "choices" => { choices = validation.table(path, value) }
+                    "autocomplete" => { autocomplete = validation.strings(path, value) }
+                    "autocomplete_file" => { autocomplete_file = validation.string(path, value) }
+                    &_ => validation.unknown_key(&option_path, id.as_str())
                 }
             }
 
-            let command_data = CommandResponse {
+            // min and max mean a length for strings and a value for numbers
+            let mut option = CommandOption {
                 name,
                 description,
-                message: MessageData {
-                    content,
-                    embeds,
-                    components,
-                },
+                name_localizations,
+                description_localizations,
+                r#type: option_type,
+                required,
+                min_value: None,
+                max_value: None,
+                min_length: None,
+                max_length: None,
+                choices: vec![],
+                autocomplete,
+                autocomplete_file,
+                options: vec![],
             };
-            new_commands.push(command_data);
-            // COMMAND_RESPONSES.lock().unwrap().push(command_data);
+            let min_path = join(&option_path, "min");
+            let max_path = join(&option_path, "max");
+            match option_type {
+                CommandOptionType::String => {
+                    option.min_length = min.and_then(|min| validation.uint(&min_path, min));
+                    option.max_length = max.and_then(|max| validation.uint(&max_path, max));
+                }
+                CommandOptionType::Integer => {
+                    option.min_value = min.and_then(|min| validation.int(&min_path, min)).map(Value::from);
+                    option.max_value = max.and_then(|max| validation.int(&max_path, max)).map(Value::from);
+                }
+                CommandOptionType::Number => {
+                    option.min_value = min.and_then(|min| validation.float(&min_path, min)).map(Value::from);
+                    option.max_value = max.and_then(|max| validation.float(&max_path, max)).map(Value::from);
+                }
+                _ => {}
+            }
+            let choices_path = join(&option_path, "choices");
+            option.choices = choices.into_iter()
+                .filter_map(|(name, value)| {
+                    let path = join(&choices_path, name.as_str());
+                    let value = match option_type {
+                        CommandOptionType::Integer => Value::from(validation.int(&path, value)?),
+                        CommandOptionType::Number => Value::from(validation.float(&path, value)?),
+                        _ => Value::from(validation.string(&path, value)?)
+                    };
+                    Some(CommandOptionChoice { name, value })
+                })
+                .collect();
+            options.push(option);
         }
-        let mut w = COMMAND_RESPONSES.write().unwrap();
-        *w = new_commands;
-        let _ = w.deref();
+        options
     }
 
     pub struct CommandData {
         pub name: String,
+        pub r#type: CommandType,
         pub description: String,
+        pub name_localizations: BTreeMap<String, String>,
+        pub description_localizations: BTreeMap<String, String>,
+        pub options: Vec<CommandOption>,
         pub integration_types: Vec<IntegrationType>,
         pub contexts: Vec<InteractionContext>
     }
 
     impl CommandData {
         pub fn to_body(&self) -> String {
+            self.to_json().to_string()
+        }
+
+        pub fn to_json(&self) -> Value {
             let integration_types: Vec<u8> = self.integration_types
                     .iter()
                     .map(|integration| integration.raw())
@@ -322,28 +1282,299 @@ pub mod init_commands {
                     .iter()
                     .map(|context| context.raw())
                     .collect();
-            let value: Value = json!({
+            // context menu commands have an empty description and no options
+            let chat_input = self.r#type == CommandType::ChatInput;
+            let mut value: Value = json!({
+                "type": self.r#type.raw(),
                 "name": self.name,
-                "description": self.description,
+                "description": if chat_input { self.description.as_str() } else { "" },
                 "integration_types": integration_types,
                 "contexts": contexts
             });
-            value.to_string()
+            if !self.name_localizations.is_empty() {
+                value["name_localizations"] = json!(self.name_localizations);
+            }
+            if !self.description_localizations.is_empty() && chat_input {
+                value["description_localizations"] = json!(self.description_localizations);
+            }
+            if !self.options.is_empty() && chat_input {
+                let options: Vec<Value> = self.options
+                    .iter()
+                    .map(|option| option.to_json())
+                    .collect();
+                value["options"] = Value::from(options);
+            }
+            value
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct CommandOption {
+        pub name: String,
+        pub description: String,
+        pub name_localizations: BTreeMap<String, String>,
+        pub description_localizations: BTreeMap<String, String>,
+        pub r#type: CommandOptionType,
+        pub required: bool,
+        pub min_value: Option<Value>,
+        pub max_value: Option<Value>,
+        pub min_length: Option<u16>,
+        pub max_length: Option<u16>,
+        pub choices: Vec<CommandOptionChoice>,
+        // suggestions listed in Commands.yml and a file with one suggestion per line
+        pub autocomplete: Vec<String>,
+        pub autocomplete_file: Option<String>,
+        // the options of a subcommand, or the subcommands of a group
+        pub options: Vec<CommandOption>
+    }
+
+    impl CommandOption {
+        pub fn to_json(&self) -> Value {
+            let mut value: Value = json!({
+                "type": self.r#type.raw(),
+                "name": self.name,
+                "description": self.description
+            });
+            if self.required {
+                value["required"] = Value::from(true);
+            }
+            if !self.name_localizations.is_empty() {
+                value["name_localizations"] = json!(self.name_localizations);
+            }
+            if !self.description_localizations.is_empty() {
+                value["description_localizations"] = json!(self.description_localizations);
+            }
+            if let Some(min_value) = &self.min_value {
+                value["min_value"] = min_value.clone();
+            }
+            if let Some(max_value) = &self.max_value {
+                value["max_value"] = max_value.clone();
+            }
+            if let Some(min_length) = self.min_length {
+                value["min_length"] = Value::from(min_length);
+            }
+            if let Some(max_length) = self.max_length {
+                value["max_length"] = Value::from(max_length);
+            }
+            if !self.choices.is_empty() {
+                let choices: Vec<Value> = self.choices
+                    .iter()
+                    .map(|choice| json!({"name": choice.name, "value": choice.value}))
+                    .collect();
+                value["choices"] = Value::from(choices);
+            }
+            if self.has_autocomplete() {
+                value["autocomplete"] = Value::from(true);
+            }
+            if !self.options.is_empty() {
+                let options: Vec<Value> = self.options
+                    .iter()
+                    .map(|option| option.to_json())
+                    .collect();
+                value["options"] = Value::from(options);
+            }
+            value
         }
+
+        pub fn has_autocomplete(&self) -> bool {
+            !self.autocomplete.is_empty() || self.autocomplete_file.is_some()
+        }
+
+        /// Every autocomplete suggestion, the file is read each time so it can be edited while running
+        pub fn suggestions(&self) -> Vec<String> {
+            let mut suggestions = self.autocomplete.clone();
+            if let Some(path) = &self.autocomplete_file {
+                match fs::read_to_string(path) {
+                    Ok(text) => {
+                        suggestions.extend(text.lines()
+                            .map(|line| line.trim().to_string())
+                            .filter(|line| !line.is_empty()));
+                    }
+                    Err(err) => {
+                        println!("Unable to read autocomplete file {} for option {}. {}", path, self.name, err);
+                    }
+                }
+            }
+            suggestions
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct CommandOptionChoice {
+        pub name: String,
+        pub value: Value
     }
 
     #[derive(Debug, Clone)]
     pub struct CommandResponse {
         pub name: String,
+        pub r#type: CommandType,
         pub description: String,
+        pub name_localizations: BTreeMap<String, String>,
+        pub description_localizations: BTreeMap<String, String>,
+        pub options: Vec<CommandOption>,
+        pub integration_types: Vec<IntegrationType>,
+        pub contexts: Vec<InteractionContext>,
+        // registered to these guilds instead of globally when not empty
+        pub guilds: Vec<String>,
+        pub message: MessageData,
+        // translated messages keyed by discord locale
+        pub locales: BTreeMap<String, MessageData>,
+        // a command with subcommands only groups them and never responds itself
+        pub subcommands: Vec<CommandResponse>,
+        // a form shown when the command is used, the message is then the reply to the submitted form
+        pub modal: Option<Modal>,
+        // answered from the threadpool after a deferred response instead of straight away
+        pub defer: bool,
+        // messages sent after the response, in order
+        pub followups: Vec<FollowUp>,
+        // messages one of which is picked at random instead of the default message
+        pub variants: Vec<Variant>,
+        // never pick the variant a user got last time
+        pub no_repeat: bool
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Variant {
+        // how likely the variant is picked relative to the others
+        pub weight: u32,
+        pub message: MessageData,
+        // translated messages keyed by discord locale
+        pub locales: BTreeMap<String, MessageData>
+    }
+
+    impl Variant {
+        /// Picks the message for the user's locale the same way commands do
+        pub fn message_for(&self, locale: Option<&str>) -> &MessageData {
+            localized(&self.message, &self.locales, locale)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct FollowUp {
+        // how long to wait after the previous message
+        pub delay: Duration,
         pub message: MessageData
     }
 
+    impl CommandResponse {
+        /// Finds the (sub)command a path of names such as ["faq", "config", "proxy"] leads to
+        pub fn find(commands: &[CommandResponse], path: &[String]) -> Option<CommandResponse> {
+            let (name, rest) = path.split_first()?;
+            let command = commands.iter().find(|command| &command.name == name)?;
+            if rest.is_empty() {
+                return Some(command.clone());
+            }
+            CommandResponse::find(&command.subcommands, rest)
+        }
+
+        /// Registers a subcommand group or subcommand as an option of its parent
+        fn to_subcommand_option(&self) -> CommandOption {
+            let (option_type, options) = if self.subcommands.is_empty() {
+                (CommandOptionType::SubCommand, self.options.clone())
+            } else {
+                (CommandOptionType::SubCommandGroup, self.subcommands.iter().map(|subcommand| subcommand.to_subcommand_option()).collect())
+            };
+            CommandOption {
+                name: self.name.clone(),
+                description: self.description.clone(),
+                name_localizations: self.name_localizations.clone(),
+                description_localizations: self.description_localizations.clone(),
+                r#type: option_type,
+                required: false,
+                min_value: None,
+                max_value: None,
+                min_length: None,
+                max_length: None,
+                choices: vec![],
+                autocomplete: vec![],
+                autocomplete_file: None,
+                options,
+            }
+        }
+
+        /// Picks the message for the user's locale, trying the exact locale (e.g. es-ES),
+        /// then the language on its own (es) and falling back to the default message
+        pub fn message_for(&self, locale: Option<&str>) -> &MessageData {
+            localized(&self.message, &self.locales, locale)
+        }
+
+        /// Whether any message the command can respond with has files to upload
+        pub fn uploads_files(&self) -> bool {
+            !self.message.files.is_empty()
+                || self.locales.values().any(|message| !message.files.is_empty())
+                || self.variants.iter().any(|variant| {
+                    !variant.message.files.is_empty() || variant.locales.values().any(|message| !message.files.is_empty())
+                })
+        }
+    }
+
+    /// Tries the exact locale (e.g. es-ES), then the language on its own (es) and falls back to the default message
+    fn localized<'a>(message: &'a MessageData, locales: &'a BTreeMap<String, MessageData>, locale: Option<&str>) -> &'a MessageData {
+        let Some(locale) = locale else {
+            return message;
+        };
+        let language = locale.split('-').next().unwrap();
+        locales.get(locale)
+            .or_else(|| locales.get(language))
+            .unwrap_or(message)
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Modal {
+        pub title: String,
+        pub inputs: Vec<TextInput>
+    }
+
+    impl Modal {
+        /// The type 9 interaction response that shows the modal
+        pub fn to_response(&self, custom_id: &str) -> Value {
+            let rows: Vec<Value> = self.inputs
+                .iter()
+                .map(|input| json!({"type": 1, "components": [input]}))
+                .collect();
+            json!({
+                "type": 9,
+                "data": {
+                    "custom_id": custom_id,
+                    "title": self.title,
+                    "components": rows
+                }
+            })
+        }
+    }
+
     #[derive(Debug, Clone, Serialize)]
+    pub struct TextInput {
+        pub r#type: u8,
+        pub custom_id: String,
+        pub label: String,
+        pub style: u8,
+        pub required: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub min_length: Option<u16>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_length: Option<u16>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub placeholder: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub value: Option<String>
+    }
+
+    #[derive(Debug, Clone, Default, Serialize)]
     pub struct MessageData {
         pub content: String,
         pub embeds: Vec<MessageEmbed>,
-        pub components: Vec<ActionRow>
+        pub components: Vec<ActionRow>,
+        #[serde(skip_serializing_if = "is_zero")]
+        pub flags: u64,
+        // local files uploaded with the message, embeds can show them through attachment://<file name>
+        #[serde(skip)]
+        pub files: Vec<String>
+    }
+
+    fn is_zero(flags: &u64) -> bool {
+        *flags == 0
     }
 
     #[derive(Debug, Clone, Serialize)]
@@ -395,17 +1626,253 @@ pub mod init_commands {
         pub inline: bool
     }
 
-    // This will only support url buttons for now as there is no good (easy) way of configuring functionality for custom_id buttons
     #[derive(Debug, Clone, Serialize)]
     pub struct ActionRow {
         pub r#type: u8,
-        pub components: Vec<UrlButtonComponent>
+        pub components: Vec<Component>
     }
+
+    impl ActionRow {
+        pub fn has_buttons(&self) -> bool {
+            self.components.iter().any(|component| matches!(component, Component::Button(_)))
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(untagged)]
+    pub enum Component {
+        Button(ButtonComponent),
+        Select(SelectComponent)
+    }
+
+    // link buttons have a url, every other style has a custom_id and a configured response
     #[derive(Debug, Clone, Serialize)]
-    pub struct UrlButtonComponent {
+    pub struct ButtonComponent {
         pub r#type: u8,
         pub style: u8,
         pub label: String,
-        pub url: String
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub custom_id: Option<String>,
+        #[serde(skip)]
+        pub response: Option<ComponentResponse>
+    }
+
+    // string selects list their options, user, role, mentionable and channel selects are filled in by discord
+    #[derive(Debug, Clone, Serialize)]
+    pub struct SelectComponent {
+        pub r#type: u8,
+        pub custom_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub placeholder: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub min_values: Option<u8>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_values: Option<u8>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub options: Vec<SelectOption>,
+        #[serde(skip)]
+        pub response: Option<ComponentResponse>
+    }
+
+    impl SelectComponent {
+        /// The response for the chosen values, which is the response of the first chosen option
+        /// when it has one and otherwise the response of the whole select menu
+        fn response_for(&self, values: &[String]) -> Option<ComponentResponse> {
+            values.first()
+                .and_then(|value| self.options.iter().find(|option| &option.value == value))
+                .and_then(|option| option.response.clone())
+                .or_else(|| self.response.clone())
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct SelectOption {
+        pub label: String,
+        pub value: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub description: Option<String>,
+        #[serde(skip)]
+        pub response: Option<ComponentResponse>
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ComponentAction {
+        // reply with a new message
+        Message { ephemeral: bool },
+        // replace the message the component is on
+        Update,
+        // acknowledge without changing anything
+        DeferUpdate
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct ComponentResponse {
+        pub action: ComponentAction,
+        pub message: MessageData
+    }
+
+    /// Finds the configured response to the component with the given custom_id and the values chosen
+    /// in it, preferring the message for the user's locale
+    pub fn find_component_response(custom_id: &str, values: &[String], locale: Option<&str>) -> Option<ComponentResponse> {
+        get_command_responses()
+            .iter()
+            .find_map(|command| find_in_command(command, custom_id, values, locale))
+    }
+
+    fn find_in_command(command: &CommandResponse, custom_id: &str, values: &[String], locale: Option<&str>) -> Option<ComponentResponse> {
+        find_in_message(command.message_for(locale), custom_id, values)
+            .or_else(|| command.variants.iter().find_map(|variant| {
+                find_in_message(variant.message_for(locale), custom_id, values)
+                    .or_else(|| variant.locales.values().find_map(|message| find_in_message(message, custom_id, values)))
+            }))
+            .or_else(|| command.locales.values().find_map(|message| find_in_message(message, custom_id, values)))
+            .or_else(|| command.followups.iter().find_map(|followup| find_in_message(&followup.message, custom_id, values)))
+            .or_else(|| command.subcommands.iter().find_map(|subcommand| find_in_command(subcommand, custom_id, values, locale)))
+    }
+
+    fn find_in_message(message: &MessageData, custom_id: &str, values: &[String]) -> Option<ComponentResponse> {
+        for row in &message.components {
+            for component in &row.components {
+                let (found, responses) = match component {
+                    Component::Button(button) => {
+                        let found = if button.custom_id.as_deref() == Some(custom_id) { button.response.clone() } else { None };
+                        (found, button.response.iter().collect::<Vec<&ComponentResponse>>())
+                    }
+                    Component::Select(select) => {
+                        let found = if select.custom_id == custom_id { select.response_for(values) } else { None };
+                        let responses = select.response.iter()
+                            .chain(select.options.iter().filter_map(|option| option.response.as_ref()))
+                            .collect();
+                        (found, responses)
+                    }
+                };
+                if found.is_some() {
+                    return found;
+                }
+                // responses can have components of their own
+                if let Some(found) = responses.into_iter().find_map(|response| find_in_message(&response.message, custom_id, values)) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{env, fs};
+        use super::{find_in_command, read_commands};
+
+        #[test]
+        fn reads_the_example_commands() {
+            let commands = read_commands("ExampleCommands.yml").unwrap();
+            assert!(commands.iter().any(|command| command.name == "faq" && command.subcommands.len() == 2));
+        }
+
+        #[test]
+        fn finds_buttons_in_followups() {
+            let path = env::temp_dir().join("selfuserbot-followup-commands.yml");
+            fs::write(&path, "
+steps:
+  description: 'Walks you through it'
+  messages:
+    1:
+      content: 'First step'
+    2:
+      content: 'Second step'
+      buttons:
+        1:
+          label: 'Done'
+          custom_id: 'steps_done'
+          response:
+            type: 'message'
+            content: 'Well done'
+").unwrap();
+            let commands = read_commands(path.to_str().unwrap()).unwrap();
+            let response = find_in_command(&commands[0], "steps_done", &[], None).unwrap();
+            assert_eq!(response.message.content, "Well done");
+        }
+
+        #[test]
+        fn reports_every_problem_with_its_path() {
+            let path = env::temp_dir().join("selfuserbot-invalid-commands.yml");
+            fs::write(&path, "
+faq:
+  description: 'Questions'
+  desciption: 'Typo'
+  type: 'slash'
+  embeds:
+    1:
+      title: 'Fine'
+      colour: 'ff0000'
+    2:
+      color: 'zz0000'
+      fields: 'not a table'
+  options:
+    amount:
+      type: 'integer'
+      description: 'How many'
+      min: 'lots'
+      requried: true
+  buttons:
+    1:
+      label: 'Download'
+      custom_id: 'download'
+      response:
+        files: ['release.zip']
+group:
+  description: 'Groups'
+  options:
+    text:
+      description: 'Never registered'
+  subcommands:
+    one:
+      description: 'One'
+").unwrap();
+            let report = read_commands(path.to_str().unwrap()).unwrap_err();
+            assert!(report.starts_with("Commands.yml has 9 problem(s):"), "{}", report);
+            assert!(report.contains("faq.buttons.1.response.files: files can't be sent in response to a button or select menu"), "{}", report);
+            assert!(report.contains("group.options: a command with subcommands can't have options"), "{}", report);
+            assert!(report.contains("faq: unknown key 'desciption'"), "{}", report);
+            assert!(report.contains("faq.embeds.1: unknown key 'colour'"), "{}", report);
+            assert!(report.contains("faq.options.amount: unknown key 'requried'"), "{}", report);
+            assert!(report.contains("faq.type: unknown command type 'slash'"), "{}", report);
+            assert!(report.contains("faq.embeds.2.color: invalid hex \"zz0000\""), "{}", report);
+            assert!(report.contains("faq.embeds.2.fields: expected a table"), "{}", report);
+            assert!(report.contains("faq.options.amount.min: expected a whole number"), "{}", report);
+        }
+
+        #[test]
+        fn reports_commands_over_discords_limits() {
+            let path = env::temp_dir().join("selfuserbot-limits-commands.yml");
+            let buttons: String = (1..=6).map(|index| format!("    {}:\n      label: 'Button'\n      url: 'ftp://example.com'\n", index)).collect();
+            fs::write(&path, format!("
+search!:
+  description: '{}'
+  content: '{}'
+  options:
+    engine:
+      description: 'Where to search'
+      autocomplete: ['web']
+      choices:
+        web: 'web'
+  buttons:
+{}
+Show user info:
+  type: 'user'
+  name_localizations:
+    de: 'Benutzerinfo anzeigen'
+", "d".repeat(101), "c".repeat(2001), buttons)).unwrap();
+            let report = read_commands(path.to_str().unwrap()).unwrap_err();
+            assert!(!report.contains("show user info"), "{}", report);
+            assert!(report.contains("search!: \"search!\" can only contain lowercase letters, numbers, - and _"), "{}", report);
+            assert!(report.contains("search!.description: descriptions have to be 1 to 100 characters long, found 101"), "{}", report);
+            assert!(report.contains("search!.content: at most 2000 characters are allowed, found 2001"), "{}", report);
+            assert!(report.contains("search!.options.engine: options can't have both choices and autocomplete"), "{}", report);
+            assert!(report.contains("search!.buttons: at most 5 buttons are allowed, found 6"), "{}", report);
+            assert!(report.contains("search!.buttons.1.url: link buttons need an http, https or discord url, found \"ftp://example.com\""), "{}", report);
+        }
     }
-}
\ No newline at end of file
+}
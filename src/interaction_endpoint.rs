@@ -1,25 +1,236 @@
 pub mod command_handler {
+    use std::collections::HashMap;
     use std::sync::mpsc::Sender;
-    use std::time::Duration;
+    use std::sync::{Mutex, RwLock};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
     use actix_web::{App, HttpResponse, HttpServer, post, Responder};
     use actix_web::http::header::HeaderMap;
     use actix_web::web::{Bytes};
     use ed25519_dalek::{PublicKey, Signature, SignatureError, Verifier};
+    use lazy_static::lazy_static;
     use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use crate::autocomplete::autocomplete;
+    use crate::components::components;
+    use crate::limits::limits;
+    use crate::variants::variants;
+    use crate::init_commands::init_commands::{get_command_responses, CommandResponse, EPHEMERAL, SUPPRESS_NOTIFICATIONS};
+    use crate::templates::templates::{render_message, render_modal, TemplateContext};
+
+    lazy_static! {
+        static ref PUB_KEY: RwLock<Option<PublicKey>> = RwLock::new(None);
+    }
+    lazy_static! {
+        static ref SENDER: RwLock<Option<Sender<DeferredCommand>>> = RwLock::new(None);
+    }
+    lazy_static! {
+        // how far the signed timestamp of a request may be from now
+        static ref TIMESTAMP_WINDOW: RwLock<Duration> = RwLock::new(Duration::from_secs(DEFAULT_TIMESTAMP_WINDOW));
+    }
+    lazy_static! {
+        // the interactions received within the timestamp window and when, older ones are rejected by their timestamp
+        static ref SEEN_INTERACTIONS: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+    }
+
+    pub const DEFAULT_TIMESTAMP_WINDOW: u64 = 300;
+
+    pub fn set_timestamp_window(seconds: u64) {
+        *TIMESTAMP_WINDOW.write().unwrap() = Duration::from_secs(seconds);
+    }
 
     #[derive(Serialize, Deserialize)]
     pub struct InteractionOption {
         pub name: String,
         pub r#type: u8,
-        pub value: Option<String>
+        pub value: Option<Value>,
+        // set on the option the user is typing in when autocompleting
+        pub focused: Option<bool>,
+        // the options of a subcommand or the subcommand of a group
+        pub options: Option<Vec<InteractionOption>>
     }
 
+    // component interactions and modal submissions have no id, name or type, only a custom_id
     #[derive(Serialize, Deserialize)]
     pub struct InteractionData {
+        #[serde(default)]
         pub id: String,
+        #[serde(default)]
         pub name: String,
+        #[serde(default)]
         pub r#type: u8,
-        pub options: Option<Vec<InteractionOption>>
+        pub options: Option<Vec<InteractionOption>>,
+        pub custom_id: Option<String>,
+        pub component_type: Option<u8>,
+        // the values chosen in a select menu
+        pub values: Option<Vec<String>>,
+        // the rows of text inputs of a submitted modal
+        pub components: Option<Vec<SubmittedRow>>,
+        // the user or message a context menu command was used on
+        pub target_id: Option<String>,
+        pub resolved: Option<ResolvedData>
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SubmittedRow {
+        pub components: Vec<SubmittedInput>
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SubmittedInput {
+        pub custom_id: String,
+        pub value: Option<String>
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ResolvedData {
+        pub users: Option<HashMap<String, User>>,
+        pub messages: Option<HashMap<String, ResolvedMessage>>
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ResolvedMessage {
+        pub id: String,
+        pub channel_id: String,
+        pub content: String,
+        pub author: User,
+        pub timestamp: String
+    }
+
+    impl InteractionData {
+        /// The names leading to the invoked command, e.g. ["faq", "config", "proxy"] for `/faq config proxy`
+        pub fn command_path(&self) -> Vec<String> {
+            let mut path = vec![self.name.clone()];
+            let mut options = self.options.as_deref().unwrap_or_default();
+            while let Some(subcommand) = options.iter().find(|option| option.r#type == 1 || option.r#type == 2) {
+                path.push(subcommand.name.clone());
+                options = subcommand.options.as_deref().unwrap_or_default();
+            }
+            path
+        }
+
+        /// The options the user filled in for the invoked (sub)command
+        pub fn leaf_options(&self) -> &[InteractionOption] {
+            let mut options = self.options.as_deref().unwrap_or_default();
+            while let Some(subcommand) = options.iter().find(|option| option.r#type == 1 || option.r#type == 2) {
+                options = subcommand.options.as_deref().unwrap_or_default();
+            }
+            options
+        }
+
+        /// Collects the values of the options the user filled in, keyed by option name.
+        /// Strings are taken as-is and every other type uses its JSON representation
+        pub fn option_values(&self) -> HashMap<String, String> {
+            let mut values: HashMap<String, String> = HashMap::new();
+            for option in self.leaf_options() {
+                let value = match &option.value {
+                    Some(Value::String(value)) => value.clone(),
+                    Some(value) => value.to_string(),
+                    None => continue
+                };
+                values.insert(option.name.clone(), value);
+            }
+            values
+        }
+
+        /// Finds the configured (sub)command that was invoked, or whose modal was submitted
+        pub fn find_command(&self) -> Option<CommandResponse> {
+            let (command_type, path) = match self.custom_id.as_ref().and_then(|custom_id| custom_id.strip_prefix("modal:")) {
+                Some(command) => {
+                    let (command_type, path) = command.split_once(':')?;
+                    (command_type.parse::<u8>().ok()?, path.split('/').map(|name| name.to_string()).collect())
+                }
+                None => (self.r#type, self.command_path())
+            };
+            // slash commands and context menu commands can share a name
+            let commands: Vec<CommandResponse> = get_command_responses()
+                .into_iter()
+                .filter(|command| command.r#type.raw() == command_type)
+                .collect();
+            CommandResponse::find(&commands, &path)
+        }
+
+        /// The custom_id given to the modal of the invoked command, which leads back to the command on submit
+        pub fn modal_custom_id(&self) -> String {
+            format!("modal:{}", self.command_key())
+        }
+
+        /// The type and full path of the invoked command, which tells apart subcommands
+        /// sharing a name and commands of different types sharing a name
+        pub fn command_key(&self) -> String {
+            match self.custom_id.as_ref().and_then(|custom_id| custom_id.strip_prefix("modal:")) {
+                Some(command) => command.to_string(),
+                None => format!("{}:{}", self.r#type, self.command_path().join("/"))
+            }
+        }
+
+        /// The values of a submitted modal, keyed by the custom_id of each text input
+        pub fn submitted_values(&self) -> HashMap<String, String> {
+            let mut values: HashMap<String, String> = HashMap::new();
+            for row in self.components.iter().flatten() {
+                for input in &row.components {
+                    values.insert(input.custom_id.clone(), input.value.clone().unwrap_or_default());
+                }
+            }
+            values
+        }
+
+        /// The values chosen in a user, role, mentionable or channel select menu as mentions
+        pub fn selected_mentions(&self) -> Vec<String> {
+            let users = self.resolved.as_ref().and_then(|resolved| resolved.users.as_ref());
+            self.values.iter().flatten()
+                .map(|id| match self.component_type {
+                    Some(5) => format!("<@{}>", id),
+                    Some(6) => format!("<@&{}>", id),
+                    Some(8) => format!("<#{}>", id),
+                    // mentionable selects can hold users and roles
+                    Some(7) if users.is_some_and(|users| users.contains_key(id)) => format!("<@{}>", id),
+                    Some(7) => format!("<@&{}>", id),
+                    _ => id.clone()
+                })
+                .collect()
+        }
+
+        /// The user a user command was used on
+        pub fn target_user(&self) -> Option<&User> {
+            self.resolved.as_ref()?.users.as_ref()?.get(self.target_id.as_ref()?)
+        }
+
+        /// The message a message command was used on
+        pub fn target_message(&self) -> Option<&ResolvedMessage> {
+            self.resolved.as_ref()?.messages.as_ref()?.get(self.target_id.as_ref()?)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct User {
+        pub id: String,
+        pub username: String,
+        pub global_name: Option<String>,
+        pub avatar: Option<String>
+    }
+
+    impl User {
+        /// The name shown in the client, preferring the display name over the username
+        pub fn display_name(&self) -> &str {
+            self.global_name.as_deref().unwrap_or(self.username.as_str())
+        }
+
+        /// The url of the user's avatar, or of the default avatar discord gives users without one
+        pub fn avatar_url(&self) -> String {
+            match &self.avatar {
+                Some(hash) => format!("https://cdn.discordapp.com/avatars/{}/{}.png", self.id, hash),
+                None => {
+                    let index = self.id.parse::<u64>().map(|id| (id >> 22) % 6).unwrap_or(0);
+                    format!("https://cdn.discordapp.com/embed/avatars/{}.png", index)
+                }
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct InteractionMember {
+        pub user: User,
+        pub nick: Option<String>
     }
 
     #[derive(Serialize, Deserialize)]
@@ -28,46 +239,148 @@ pub mod command_handler {
         pub application_id: String,
         pub r#type: u8,
         pub token: String,
-        pub data: Option<InteractionData>
+        pub data: Option<InteractionData>,
+        pub guild_id: Option<String>,
+        pub channel_id: Option<String>,
+        pub member: Option<InteractionMember>,
+        pub user: Option<User>,
+        pub locale: Option<String>
+    }
+
+    impl Interaction {
+        /// The user that triggered the interaction. Discord sends `member` in guilds and `user` everywhere else
+        pub fn invoking_user(&self) -> Option<&User> {
+            match &self.member {
+                Some(member) => Some(&member.user),
+                None => self.user.as_ref()
+            }
+        }
     }
 
 
+    /// A command waiting to be answered from the threadpool
+    pub struct DeferredCommand {
+        pub interaction: Interaction,
+        // the variant picked when the command was acknowledged
+        pub variant: Option<usize>
+    }
+
     #[post("/")]
     pub async fn post_interaction(req: actix_web::HttpRequest, bytes: Bytes) -> impl Responder {
         let body = String::from_utf8(bytes.to_vec()).map_err(|_| HttpResponse::BadRequest().finish()).unwrap();
-        unsafe {
-            match validate_discord_signature(req.headers(), &body, &(PUB_KEY.unwrap())) {
-                Ok(_) => {
-                    let interaction: Interaction = serde_json::from_str(&body.as_str()).unwrap();
-                    if &interaction.r#type == &u8::from(1u8) {
-                        HttpResponse::Ok()
-                            .insert_header(("Content-Type", "application/json"))
-                            .body("{\"type\": 1}")
-                    } else {
-                        let _ = &SENDER.clone().unwrap().send(interaction);
-                        HttpResponse::Ok()
-                            .insert_header(("Content-Type", "application/json"))
-                            .body("{\"type\": 5}")
-                    }
+        let pub_key = PUB_KEY.read().unwrap().unwrap();
+        match validate_discord_signature(req.headers(), &body, &pub_key) {
+            Ok(_) => {
+                if !validate_timestamp(req.headers()) {
+                    println!("Discord signature timestamp is outside of the allowed window");
+                    return HttpResponse::Unauthorized()
+                        .body("request timestamp out of range");
                 }
-                Err(_) => {
-                    println!("Invalid discord signature");
-                    HttpResponse::MethodNotAllowed()
-                        .body("invalid request signature")
+                let interaction: Interaction = serde_json::from_str(body.as_str()).unwrap();
+                // pings are answered the same way every time, anything else is only handled once
+                if interaction.r#type != 1u8 && !first_delivery(interaction.id.as_str()) {
+                    println!("Ignoring interaction {} as it was already handled", interaction.id);
+                    return HttpResponse::Conflict()
+                        .body("interaction already handled");
                 }
+                if interaction.r#type == 1u8 {
+                    HttpResponse::Ok()
+                        .insert_header(("Content-Type", "application/json"))
+                        .body("{\"type\": 1}")
+                } else if interaction.r#type == 4u8 {
+                    // autocomplete has to be answered straight away
+                    HttpResponse::Ok()
+                        .insert_header(("Content-Type", "application/json"))
+                        .body(autocomplete::respond(&interaction).to_string())
+                } else if interaction.r#type == 3u8 {
+                    // buttons and select menus are answered straight away from their configured response
+                    HttpResponse::Ok()
+                        .insert_header(("Content-Type", "application/json"))
+                        .body(components::respond(&interaction).to_string())
+                } else if let Some(modal) = modal_response(&interaction) {
+                    // a modal has to be the first response, so it can't be deferred
+                    HttpResponse::Ok()
+                        .insert_header(("Content-Type", "application/json"))
+                        .body(modal.to_string())
+                } else if let Some(message) = immediate_response(&interaction) {
+                    // static responses don't need a second round-trip
+                    HttpResponse::Ok()
+                        .insert_header(("Content-Type", "application/json"))
+                        .body(message.to_string())
+                } else {
+                    // pick the variant now, so the acknowledgement is ephemeral exactly when the message is
+                    let variant = pick_variant(&interaction);
+                    let deferred = deferred_response(&interaction, variant);
+                    let _ = SENDER.read().unwrap().as_ref().unwrap().send(DeferredCommand { interaction, variant });
+                    HttpResponse::Ok()
+                        .insert_header(("Content-Type", "application/json"))
+                        .body(deferred.to_string())
+                }
+            }
+            Err(_) => {
+                println!("Invalid discord signature");
+                HttpResponse::MethodNotAllowed()
+                    .body("invalid request signature")
             }
         }
     }
-    static mut PUB_KEY: Option<PublicKey> = None;
-    static mut SENDER: Option<Sender<Interaction>> = None;
+
+    /// The response opening the modal of the invoked command, if it has one
+    fn modal_response(interaction: &Interaction) -> Option<Value> {
+        if interaction.r#type != 2 {
+            return None;
+        }
+        let data = interaction.data.as_ref()?;
+        let modal = data.find_command()?.modal?;
+        let context = TemplateContext::from_interaction(interaction);
+        let mut modal = render_modal(&modal, &context);
+        limits::fit_modal(&mut modal, data.name.as_str());
+        Some(modal.to_response(data.modal_custom_id().as_str()))
+    }
+
+    /// The message answering the invoked command right away, unless it is deferred
+    fn immediate_response(interaction: &Interaction) -> Option<Value> {
+        let command = interaction.data.as_ref()?.find_command()?;
+        // follow-ups and file uploads are sent from the threadpool, which then also sends the response
+        if command.defer || !command.followups.is_empty() || command.uploads_files() {
+            return None;
+        }
+        let variant = pick_variant(interaction);
+        let context = TemplateContext::from_interaction(interaction);
+        let mut message = render_message(variants::message(&command, variant, interaction.locale.as_deref()), &context);
+        limits::fit_message(&mut message, command.name.as_str());
+        Some(json!({"type": 4, "data": message}))
+    }
+
+    fn pick_variant(interaction: &Interaction) -> Option<usize> {
+        let data = interaction.data.as_ref()?;
+        let command = data.find_command()?;
+        variants::pick(&command, data.command_key().as_str(), interaction.invoking_user().map(|user| user.id.as_str()))
+    }
+
+    /// The deferred response, which decides whether the message that replaces it is ephemeral and silent
+    /// since editing it later can only change whether embeds are suppressed
+    fn deferred_response(interaction: &Interaction, variant: Option<usize>) -> Value {
+        let flags = interaction.data.as_ref()
+            .and_then(|data| data.find_command())
+            .map(|command| variants::message(&command, variant, interaction.locale.as_deref()).flags & (EPHEMERAL | SUPPRESS_NOTIFICATIONS))
+            .unwrap_or(0);
+        if flags == 0 {
+            json!({"type": 5})
+        } else {
+            json!({"type": 5, "data": {"flags": flags}})
+        }
+    }
+
+    /// Sets the key signatures are checked against and where verified interactions are sent
+    pub fn init(publickey: PublicKey, sender: Sender<DeferredCommand>) {
+        *PUB_KEY.write().unwrap() = Some(publickey);
+        *SENDER.write().unwrap() = Some(sender);
+    }
 
     #[actix_web::main]
-    pub async unsafe fn main(address: &str, port: u16, publickey: &str, sender: Sender<Interaction>) -> std::io::Result<()> {
-        PUB_KEY = Some(PublicKey::from_bytes(
-            &hex::decode(publickey)
-                .expect("Invalid Discord publickey")
-        ).expect("Failed to create Discord publickey"));
-        SENDER = Some(sender);
+    pub async fn main(address: &str, port: u16, publickey: PublicKey, sender: Sender<DeferredCommand>) -> std::io::Result<()> {
+        init(publickey, sender);
         HttpServer::new(|| {
             App::new()
                 .service(post_interaction)
@@ -78,6 +391,26 @@ pub mod command_handler {
             .await
     }
 
+    /// Whether the signed timestamp of a request is recent enough, so old requests can't be replayed
+    pub fn validate_timestamp(headers: &HeaderMap) -> bool {
+        let Some(timestamp) = headers.get("X-Signature-Timestamp")
+            .and_then(|timestamp| timestamp.to_str().ok())
+            .and_then(|timestamp| timestamp.parse::<u64>().ok()) else {
+            return false;
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        now.abs_diff(timestamp) <= TIMESTAMP_WINDOW.read().unwrap().as_secs()
+    }
+
+    /// Remembers the interaction, returning whether it is the first time it was received
+    fn first_delivery(id: &str) -> bool {
+        let window = *TIMESTAMP_WINDOW.read().unwrap();
+        let mut seen = SEEN_INTERACTIONS.lock().unwrap();
+        // anything older than the window would be rejected by its timestamp anyway
+        seen.retain(|_, received| received.elapsed() <= window * 2);
+        seen.insert(id.to_string(), Instant::now()).is_none()
+    }
+
     pub fn validate_discord_signature(headers: &HeaderMap, body: &String, pub_key: &PublicKey) -> Result<(), SignatureError> {
         let sig_ed25519 = {
             let header_signature = headers.get("X-Signature-Ed25519");
@@ -112,6 +445,179 @@ pub mod command_handler {
             .cloned()
             .collect::<Vec<u8>>();
         pub_key
-            .verify(&content.as_slice(), &(sig_ed25519.unwrap()))
+            .verify(content.as_slice(), &(sig_ed25519.unwrap()))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{env, fs, thread};
+        use std::sync::mpsc;
+        use std::time::{SystemTime, UNIX_EPOCH};
+        use actix_web::{test as actix_test, App};
+        use actix_web::http::StatusCode;
+        use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+        use serde_json::{json, Value};
+        use crate::handle_responses::handle_responses::handle_responses;
+        use crate::discord_client::discord_client;
+        use crate::init_commands::init_commands::{get_command_responses, load_cmds, reload_commands, set_api_url, set_commands_file};
+        use crate::mock_discord::mock_discord::{MockDiscord, APPLICATION_ID};
+        use super::{init, post_interaction, InteractionData};
+
+        const COMMANDS: &str = "
+greet:
+  description: 'Greets you'
+  content: 'Hello {user.name}, you said {option.text}'
+  options:
+    text:
+      type: 'string'
+      description: 'Something to say'
+      required: true
+  name_localizations:
+    de: 'gruessen'
+  defer: true
+  silent: true
+ping:
+  description: 'Answers straight away'
+  content: 'Pong, {user.name}!'
+  ephemeral: true
+faq:
+  description: 'Questions'
+  subcommands:
+    config:
+      description: 'Configuration questions'
+      subcommands:
+        proxy:
+          description: 'Setting up a proxy'
+          content: 'Put the proxy address in Config.yml.'
+";
+
+        fn keypair() -> Keypair {
+            let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+            let public: PublicKey = (&secret).into();
+            Keypair { secret, public }
+        }
+
+        /// Posts the body to the endpoint the way discord would, returning the status and response body
+        fn post(keypair: &Keypair, body: &str, valid: bool) -> (StatusCode, String) {
+            post_at(keypair, body, valid, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs())
+        }
+
+        fn post_at(keypair: &Keypair, body: &str, valid: bool, timestamp: u64) -> (StatusCode, String) {
+            let timestamp = timestamp.to_string();
+            let mut signature = keypair.sign(format!("{}{}", timestamp, body).as_bytes()).to_bytes();
+            if !valid {
+                signature[0] ^= 1;
+            }
+            let body = body.to_string();
+            actix_web::rt::System::new().block_on(async move {
+                let app = actix_test::init_service(App::new().service(post_interaction)).await;
+                let request = actix_test::TestRequest::post()
+                    .uri("/")
+                    .insert_header(("X-Signature-Ed25519", hex::encode(signature)))
+                    .insert_header(("X-Signature-Timestamp", timestamp))
+                    .set_payload(body)
+                    .to_request();
+                let response = actix_test::call_service(&app, request).await;
+                let status = response.status();
+                let body = actix_test::read_body(response).await;
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            })
+        }
+
+        #[test]
+        fn answers_a_signed_command_through_discord() {
+            let discord = MockDiscord::start();
+            set_api_url(discord.url.as_str());
+            let commands_file = env::temp_dir().join("selfuserbot-endpoint-test.yml");
+            fs::write(&commands_file, COMMANDS).unwrap();
+            set_commands_file(commands_file.to_str().unwrap());
+            load_cmds("test-token", false);
+
+            let registered = discord.wait_for("POST", "/commands").unwrap();
+            assert_eq!(registered.authorization.as_deref(), Some("Bot test-token"));
+            assert_eq!(registered.json()["name"], "greet");
+            assert_eq!(registered.json()["options"][0]["required"], true);
+            // syncing again finds nothing to change, localized commands included
+            load_cmds("test-token", false);
+            assert!(!discord.requests().iter().any(|request| request.method == "PATCH"));
+            // a file that goes missing keeps the commands instead of removing them from discord
+            fs::remove_file(&commands_file).unwrap();
+            reload_commands(discord_client::shared(), "test-token");
+            assert_eq!(get_command_responses().len(), 3);
+            fs::write(&commands_file, "").unwrap();
+            reload_commands(discord_client::shared(), "test-token");
+            assert_eq!(get_command_responses().len(), 3);
+            assert!(!discord.requests().iter().any(|request| request.method == "DELETE"));
+            fs::write(&commands_file, COMMANDS).unwrap();
+
+            // subcommands are found by walking the options down to the invoked one
+            let data: InteractionData = serde_json::from_value(json!({
+                "name": "faq",
+                "type": 1,
+                "options": [{"name": "config", "type": 2, "options": [{"name": "proxy", "type": 1, "options": []}]}]
+            })).unwrap();
+            let command = data.find_command().unwrap();
+            assert_eq!(command.name, "proxy");
+            assert_eq!(command.message.content, "Put the proxy address in Config.yml.");
+
+            let keypair = keypair();
+            let (tx, rx) = mpsc::channel();
+            init(keypair.public, tx);
+            thread::spawn(|| handle_responses(rx, 1));
+
+            let interaction = json!({
+                "id": "200000000000000000",
+                "application_id": APPLICATION_ID,
+                "type": 2,
+                "token": "interaction-token",
+                "locale": "en-GB",
+                "channel_id": "300000000000000000",
+                "user": {"id": "400000000000000000", "username": "tester", "global_name": "Tester"},
+                "data": {
+                    "id": "500000000000000000",
+                    "name": "greet",
+                    "type": 1,
+                    "options": [{"name": "text", "type": 3, "value": "hi *there*"}]
+                }
+            }).to_string();
+
+            let (status, _) = post(&keypair, interaction.as_str(), false);
+            assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+
+            let an_hour_ago = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() - 3600;
+            let (status, _) = post_at(&keypair, interaction.as_str(), true, an_hour_ago);
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+            let (status, body) = post(&keypair, interaction.as_str(), true);
+            assert_eq!(status, StatusCode::OK);
+            // the deferred response carries the flags the later edit can't set
+            assert_eq!(serde_json::from_str::<Value>(body.as_str()).unwrap(), json!({"type": 5, "data": {"flags": 4096}}));
+
+            let (status, _) = post(&keypair, interaction.as_str(), true);
+            assert_eq!(status, StatusCode::CONFLICT);
+
+            let edit = discord.wait_for("PATCH", "/messages/@original").unwrap();
+            assert_eq!(edit.path, format!("/api/webhooks/{}/interaction-token/messages/@original", APPLICATION_ID));
+            assert_eq!(edit.json(), json!({
+                "content": "Hello Tester, you said hi \\*there\\*",
+                "embeds": [],
+                "components": []
+            }));
+
+            let interaction = json!({
+                "id": "200000000000000001",
+                "application_id": APPLICATION_ID,
+                "type": 2,
+                "token": "interaction-token",
+                "user": {"id": "400000000000000000", "username": "tester", "global_name": "Tester"},
+                "data": {"id": "500000000000000001", "name": "ping", "type": 1}
+            }).to_string();
+            let (status, body) = post(&keypair, interaction.as_str(), true);
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(serde_json::from_str::<Value>(body.as_str()).unwrap(), json!({
+                "type": 4,
+                "data": {"content": "Pong, Tester!", "embeds": [], "components": [], "flags": 64}
+            }));
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,334 @@
+pub mod limits {
+    use crate::init_commands::init_commands::{ActionRow, CommandOption, CommandResponse, CommandType, Component, ComponentResponse, MessageData, Modal};
+
+    // discord's limits, anything over them is refused with a 400
+    const MAX_NAME_LENGTH: usize = 32;
+    const MAX_DESCRIPTION_LENGTH: usize = 100;
+    const MAX_OPTIONS: usize = 25;
+    const MAX_CHOICES: usize = 25;
+    const MAX_CHOICE_NAME_LENGTH: usize = 100;
+    const MAX_CONTENT_LENGTH: usize = 2000;
+    const MAX_EMBEDS: usize = 10;
+    const MAX_EMBED_TITLE_LENGTH: usize = 256;
+    const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+    const MAX_FIELDS: usize = 25;
+    const MAX_FIELD_NAME_LENGTH: usize = 256;
+    const MAX_FIELD_VALUE_LENGTH: usize = 1024;
+    const MAX_FOOTER_LENGTH: usize = 2048;
+    const MAX_AUTHOR_NAME_LENGTH: usize = 256;
+    const MAX_EMBED_TOTAL_LENGTH: usize = 6000;
+    const MAX_ROWS: usize = 5;
+    const MAX_BUTTONS_PER_ROW: usize = 5;
+    const MAX_LABEL_LENGTH: usize = 80;
+    const MAX_CUSTOM_ID_LENGTH: usize = 100;
+    const MAX_SELECT_OPTIONS: usize = 25;
+    const MAX_PLACEHOLDER_LENGTH: usize = 150;
+    const MAX_MODAL_INPUTS: usize = 5;
+    const MAX_MODAL_TITLE_LENGTH: usize = 45;
+    const MAX_INPUT_LABEL_LENGTH: usize = 45;
+    const MAX_INPUT_PLACEHOLDER_LENGTH: usize = 100;
+    const MAX_INPUT_VALUE_LENGTH: usize = 4000;
+    const URL_SCHEMES: [&str; 3] = ["http://", "https://", "discord://"];
+
+    /// Every way the command breaks discord's limits, each prefixed with the path to the offending value
+    pub fn check_command(command: &CommandResponse, path: &str) -> Vec<String> {
+        let mut errors: Vec<String> = vec![];
+        // context menu names may have spaces and capitals, they only have a length limit
+        let check_name = |name: &str, path: &str, errors: &mut Vec<String>| {
+            if command.r#type == CommandType::ChatInput {
+                check_slash_name(name, path, errors);
+            } else if name.is_empty() || length(name) > MAX_NAME_LENGTH {
+                errors.push(format!("{}: names have to be 1 to {} characters long", path, MAX_NAME_LENGTH));
+            }
+        };
+        check_name(command.name.as_str(), path, &mut errors);
+        if command.r#type == CommandType::ChatInput {
+            check_description(command.description.as_str(), path, &mut errors);
+        }
+        for (locale, name) in &command.name_localizations {
+            check_name(name.as_str(), format!("{}.name_localizations.{}", path, locale).as_str(), &mut errors);
+        }
+        for (locale, description) in &command.description_localizations {
+            check_description(description.as_str(), format!("{}.description_localizations.{}", path, locale).as_str(), &mut errors);
+        }
+        if command.options.len() > MAX_OPTIONS {
+            errors.push(format!("{}.options: at most {} options are allowed, found {}", path, MAX_OPTIONS, command.options.len()));
+        }
+        for option in &command.options {
+            check_option(option, format!("{}.options.{}", path, option.name).as_str(), &mut errors);
+        }
+        if command.subcommands.len() > MAX_OPTIONS {
+            errors.push(format!("{}.subcommands: at most {} subcommands are allowed, found {}", path, MAX_OPTIONS, command.subcommands.len()));
+        }
+        for subcommand in &command.subcommands {
+            errors.extend(check_command(subcommand, format!("{}.subcommands.{}", path, subcommand.name).as_str()));
+        }
+
+        check_message(&command.message, path, &mut errors);
+        for (locale, message) in &command.locales {
+            check_message(message, format!("{}.locales.{}", path, locale).as_str(), &mut errors);
+        }
+        for (index, variant) in command.variants.iter().enumerate() {
+            let variant_path = format!("{}.variants.{}", path, index + 1);
+            check_message(&variant.message, variant_path.as_str(), &mut errors);
+            for (locale, message) in &variant.locales {
+                check_message(message, format!("{}.locales.{}", variant_path, locale).as_str(), &mut errors);
+            }
+        }
+        // the command's own message is the first of the sequence
+        for (index, followup) in command.followups.iter().enumerate() {
+            check_message(&followup.message, format!("{}.messages.{}", path, index + 2).as_str(), &mut errors);
+        }
+        if let Some(modal) = &command.modal {
+            check_modal(modal, format!("{}.modal", path).as_str(), &mut errors);
+        }
+        errors
+    }
+
+    /// Every way the message breaks discord's limits, each prefixed with the path to the offending value
+    pub fn check_message(message: &MessageData, path: &str, errors: &mut Vec<String>) {
+        check_length(message.content.as_str(), MAX_CONTENT_LENGTH, format!("{}.content", path).as_str(), errors);
+
+        if message.embeds.len() > MAX_EMBEDS {
+            errors.push(format!("{}.embeds: at most {} embeds are allowed, found {}", path, MAX_EMBEDS, message.embeds.len()));
+        }
+        let mut total = 0;
+        for (index, embed) in message.embeds.iter().enumerate() {
+            let embed_path = format!("{}.embeds.{}", path, index + 1);
+            let mut check = |text: &str, max: usize, key: &str| {
+                total += length(text);
+                check_length(text, max, format!("{}.{}", embed_path, key).as_str(), errors);
+            };
+            check(embed.title.as_deref().unwrap_or_default(), MAX_EMBED_TITLE_LENGTH, "title");
+            check(embed.description.as_deref().unwrap_or_default(), MAX_EMBED_DESCRIPTION_LENGTH, "description");
+            check(embed.footer.as_ref().map(|footer| footer.text.as_str()).unwrap_or_default(), MAX_FOOTER_LENGTH, "footer.text");
+            check(embed.author.as_ref().map(|author| author.name.as_str()).unwrap_or_default(), MAX_AUTHOR_NAME_LENGTH, "author.name");
+            let fields = embed.fields.as_deref().unwrap_or_default();
+            for (index, field) in fields.iter().enumerate() {
+                check(field.name.as_str(), MAX_FIELD_NAME_LENGTH, format!("fields.{}.name", index + 1).as_str());
+                check(field.value.as_str(), MAX_FIELD_VALUE_LENGTH, format!("fields.{}.value", index + 1).as_str());
+            }
+            if fields.len() > MAX_FIELDS {
+                errors.push(format!("{}.fields: at most {} fields are allowed, found {}", embed_path, MAX_FIELDS, fields.len()));
+            }
+        }
+        if total > MAX_EMBED_TOTAL_LENGTH {
+            errors.push(format!("{}.embeds: the embeds can hold at most {} characters together, found {}", path, MAX_EMBED_TOTAL_LENGTH, total));
+        }
+
+        if message.components.len() > MAX_ROWS {
+            errors.push(format!("{}: at most {} rows of buttons and select menus are allowed, found {}", path, MAX_ROWS, message.components.len()));
+        }
+        let mut selects = 0;
+        for row in &message.components {
+            if row.has_buttons() {
+                check_buttons(row, format!("{}.buttons", path).as_str(), errors);
+            } else {
+                selects += 1;
+                check_selects(row, format!("{}.selects.{}", path, selects).as_str(), errors);
+            }
+        }
+    }
+
+    /// Cuts the content of a rendered message down to what discord accepts, telling about anything
+    /// else that is still over the limits since placeholders can make a message longer than configured.
+    /// The name is the command or the custom_id of the component the message answers
+    pub fn fit_message(message: &mut MessageData, name: &str) {
+        let mut errors: Vec<String> = vec![];
+        check_message(message, name, &mut errors);
+        for error in errors {
+            println!("'{}' renders a message discord will refuse. {}", name, error);
+        }
+        truncate(&mut message.content, MAX_CONTENT_LENGTH);
+    }
+
+    /// Cuts the texts of a rendered modal down to what discord accepts, which placeholders can push over the limits
+    pub fn fit_modal(modal: &mut Modal, command: &str) {
+        let mut errors: Vec<String> = vec![];
+        check_modal(modal, command, &mut errors);
+        for error in errors {
+            println!("'{}' renders a modal discord will refuse. {}", command, error);
+        }
+        truncate(&mut modal.title, MAX_MODAL_TITLE_LENGTH);
+        for input in &mut modal.inputs {
+            truncate(&mut input.label, MAX_INPUT_LABEL_LENGTH);
+            if let Some(placeholder) = &mut input.placeholder {
+                truncate(placeholder, MAX_INPUT_PLACEHOLDER_LENGTH);
+            }
+            if let Some(value) = &mut input.value {
+                truncate(value, MAX_INPUT_VALUE_LENGTH);
+            }
+        }
+    }
+
+    fn truncate(text: &mut String, max: usize) {
+        if length(text.as_str()) > max {
+            *text = text.chars().take(max - 1).collect::<String>() + "…";
+        }
+    }
+
+    fn check_buttons(row: &ActionRow, path: &str, errors: &mut Vec<String>) {
+        if row.components.len() > MAX_BUTTONS_PER_ROW {
+            errors.push(format!("{}: at most {} buttons are allowed, found {}", path, MAX_BUTTONS_PER_ROW, row.components.len()));
+        }
+        for (index, component) in row.components.iter().enumerate() {
+            let Component::Button(button) = component else {
+                continue;
+            };
+            let path = format!("{}.{}", path, index + 1);
+            check_length(button.label.as_str(), MAX_LABEL_LENGTH, format!("{}.label", path).as_str(), errors);
+            match (&button.url, &button.custom_id) {
+                (Some(url), _) => {
+                    // placeholders are only known when the message is sent
+                    if !url.contains('{') && !URL_SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+                        errors.push(format!("{}.url: link buttons need an http, https or discord url, found \"{}\"", path, url));
+                    }
+                }
+                (None, Some(custom_id)) => {
+                    check_length(custom_id.as_str(), MAX_CUSTOM_ID_LENGTH, format!("{}.custom_id", path).as_str(), errors);
+                }
+                (None, None) => errors.push(format!("{}: buttons need either a url or a custom_id", path))
+            }
+            if let Some(response) = &button.response {
+                check_response(response, format!("{}.response", path).as_str(), errors);
+            }
+        }
+    }
+
+    fn check_selects(row: &ActionRow, path: &str, errors: &mut Vec<String>) {
+        for component in &row.components {
+            let Component::Select(select) = component else {
+                continue;
+            };
+            if select.custom_id.is_empty() {
+                errors.push(format!("{}.custom_id: select menus need a custom_id", path));
+            }
+            check_length(select.custom_id.as_str(), MAX_CUSTOM_ID_LENGTH, format!("{}.custom_id", path).as_str(), errors);
+            check_length(select.placeholder.as_deref().unwrap_or_default(), MAX_PLACEHOLDER_LENGTH, format!("{}.placeholder", path).as_str(), errors);
+            for (key, value) in [("min_values", select.min_values), ("max_values", select.max_values)] {
+                if value.is_some_and(|value| value as usize > MAX_SELECT_OPTIONS) {
+                    errors.push(format!("{}.{}: at most {} values can be chosen", path, key, MAX_SELECT_OPTIONS));
+                }
+            }
+            // only string selects list their own options
+            if select.r#type == 3 && (select.options.is_empty() || select.options.len() > MAX_SELECT_OPTIONS) {
+                errors.push(format!("{}.options: string selects need 1 to {} options, found {}", path, MAX_SELECT_OPTIONS, select.options.len()));
+            }
+            for (index, option) in select.options.iter().enumerate() {
+                let path = format!("{}.options.{}", path, index + 1);
+                check_length(option.label.as_str(), MAX_CHOICE_NAME_LENGTH, format!("{}.label", path).as_str(), errors);
+                check_length(option.value.as_str(), MAX_CHOICE_NAME_LENGTH, format!("{}.value", path).as_str(), errors);
+                check_length(option.description.as_deref().unwrap_or_default(), MAX_CHOICE_NAME_LENGTH, format!("{}.description", path).as_str(), errors);
+                if let Some(response) = &option.response {
+                    check_response(response, format!("{}.response", path).as_str(), errors);
+                }
+            }
+            if let Some(response) = &select.response {
+                check_response(response, format!("{}.response", path).as_str(), errors);
+            }
+        }
+    }
+
+    fn check_response(response: &ComponentResponse, path: &str, errors: &mut Vec<String>) {
+        check_message(&response.message, path, errors);
+    }
+
+    fn check_modal(modal: &Modal, path: &str, errors: &mut Vec<String>) {
+        check_length(modal.title.as_str(), MAX_MODAL_TITLE_LENGTH, format!("{}.title", path).as_str(), errors);
+        if modal.inputs.is_empty() || modal.inputs.len() > MAX_MODAL_INPUTS {
+            errors.push(format!("{}.inputs: modals need 1 to {} inputs, found {}", path, MAX_MODAL_INPUTS, modal.inputs.len()));
+        }
+        for (index, input) in modal.inputs.iter().enumerate() {
+            let path = format!("{}.inputs.{}", path, index + 1);
+            check_length(input.label.as_str(), MAX_INPUT_LABEL_LENGTH, format!("{}.label", path).as_str(), errors);
+            check_length(input.custom_id.as_str(), MAX_CUSTOM_ID_LENGTH, format!("{}.custom_id", path).as_str(), errors);
+            check_length(input.placeholder.as_deref().unwrap_or_default(), MAX_INPUT_PLACEHOLDER_LENGTH, format!("{}.placeholder", path).as_str(), errors);
+            check_length(input.value.as_deref().unwrap_or_default(), MAX_INPUT_VALUE_LENGTH, format!("{}.value", path).as_str(), errors);
+        }
+    }
+
+    fn check_option(option: &CommandOption, path: &str, errors: &mut Vec<String>) {
+        check_slash_name(option.name.as_str(), path, errors);
+        check_description(option.description.as_str(), path, errors);
+        for (locale, name) in &option.name_localizations {
+            check_slash_name(name.as_str(), format!("{}.name_localizations.{}", path, locale).as_str(), errors);
+        }
+        for (locale, description) in &option.description_localizations {
+            check_description(description.as_str(), format!("{}.description_localizations.{}", path, locale).as_str(), errors);
+        }
+        if option.choices.len() > MAX_CHOICES {
+            errors.push(format!("{}.choices: at most {} choices are allowed, found {}", path, MAX_CHOICES, option.choices.len()));
+        }
+        for choice in &option.choices {
+            if choice.name.is_empty() || length(choice.name.as_str()) > MAX_CHOICE_NAME_LENGTH {
+                errors.push(format!("{}.choices.{}: choice names have to be 1 to {} characters long", path, choice.name, MAX_CHOICE_NAME_LENGTH));
+            }
+        }
+        if option.has_autocomplete() && !option.choices.is_empty() {
+            errors.push(format!("{}: options can't have both choices and autocomplete", path));
+        }
+    }
+
+    /// Slash command, subcommand and option names have to be lowercase letters, numbers, - or _
+    fn check_slash_name(name: &str, path: &str, errors: &mut Vec<String>) {
+        if name.is_empty() || length(name) > MAX_NAME_LENGTH {
+            errors.push(format!("{}: names have to be 1 to {} characters long, found \"{}\"", path, MAX_NAME_LENGTH, name));
+        }
+        let valid = name.chars().all(|c| (c.is_alphanumeric() || c == '-' || c == '_') && !c.is_uppercase());
+        if !valid {
+            errors.push(format!("{}: \"{}\" can only contain lowercase letters, numbers, - and _", path, name));
+        }
+    }
+
+    fn check_description(description: &str, path: &str, errors: &mut Vec<String>) {
+        if description.is_empty() || length(description) > MAX_DESCRIPTION_LENGTH {
+            errors.push(format!("{}.description: descriptions have to be 1 to {} characters long, found {}", path, MAX_DESCRIPTION_LENGTH, length(description)));
+        }
+    }
+
+    fn check_length(text: &str, max: usize, path: &str, errors: &mut Vec<String>) {
+        let length = length(text);
+        if length > max {
+            errors.push(format!("{}: at most {} characters are allowed, found {}", path, max, length));
+        }
+    }
+
+    // discord counts characters rather than bytes
+    fn length(text: &str) -> usize {
+        text.chars().count()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::init_commands::init_commands::{MessageData, Modal, TextInput};
+        use super::{fit_message, fit_modal};
+
+        #[test]
+        fn cuts_rendered_texts_to_the_limits() {
+            let mut message = MessageData { content: "a".repeat(2500), ..MessageData::default() };
+            fit_message(&mut message, "greet");
+            assert_eq!(message.content.chars().count(), 2000);
+            assert!(message.content.ends_with('…'));
+
+            let mut modal = Modal {
+                title: "Feedback for ".to_string() + "a".repeat(40).as_str(),
+                inputs: vec![TextInput {
+                    r#type: 4,
+                    custom_id: "text".to_string(),
+                    label: "Short label".to_string(),
+                    style: 1,
+                    required: true,
+                    min_length: None,
+                    max_length: None,
+                    placeholder: Some("b".repeat(150)),
+                    value: Some("c".repeat(5000))
+                }]
+            };
+            fit_modal(&mut modal, "feedback");
+            assert_eq!(modal.title.chars().count(), 45);
+            assert_eq!(modal.inputs[0].label, "Short label");
+            assert_eq!(modal.inputs[0].placeholder.as_ref().unwrap().chars().count(), 100);
+            assert_eq!(modal.inputs[0].value.as_ref().unwrap().chars().count(), 4000);
+        }
+    }
+}
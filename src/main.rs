@@ -1,56 +1,67 @@
-use std::{fs, thread};
-use std::fs::File;
+#![allow(clippy::module_inception)]
+use std::{env, process, thread};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
-use config::Config;
-use serde_json::Value;
 use crate::handle_responses::handle_responses::handle_responses;
-use crate::interaction_endpoint::command_handler::Interaction;
+use crate::interaction_endpoint::command_handler::DeferredCommand;
 
 mod init_commands;
 mod interaction_endpoint;
 mod handle_responses;
+mod templates;
+mod sync_commands;
+mod autocomplete;
+mod components;
+mod variants;
+mod discord_client;
+mod limits;
+mod settings;
+#[cfg(test)]
+mod mock_discord;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if !fs::metadata("Config.yml").is_ok() {
-        match File::create("Config.yml") {
-            Ok(_) => {}
-            Err(_) => {
-                println!("Unable to create new config file. Please check file permissions");
-                return Ok(());
-            }
+    let args = match settings::settings::parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            println!("{}\n\n{}", err, settings::settings::USAGE);
+            process::exit(2);
         }
+    };
+    if args.help {
+        println!("{}", settings::settings::USAGE);
+        return Ok(());
     }
-    let settings = Config::builder()
-        .add_source(config::File::with_name("Config.yml"))
-        .build()
-        .unwrap();
-
-    // get webserver options
-    let webserver_section: Value = settings.get::<Value>("webserver").unwrap();
-
-    // get the address
-    let binding = webserver_section.get("address").unwrap().to_owned();
-    let address = binding.as_str().unwrap();
+    let settings = match settings::settings::load(&args) {
+        Ok(settings) => settings,
+        Err(err) => {
+            println!("{}", err);
+            process::exit(1);
+        }
+    };
 
-    // get the port
-    let binding = webserver_section.get("port").unwrap().to_owned();
-    let port: u16 = u16::try_from(binding.as_u64().unwrap()).unwrap();
+    let address = settings.webserver.address.as_str();
+    let port = settings.webserver.port;
+    let publickey = settings.discord.publickey;
+    let token = settings.discord.token.as_str();
+    interaction_endpoint::command_handler::set_timestamp_window(settings.webserver.timestamp_window);
+    init_commands::init_commands::set_api_url(settings.discord.api_url.as_str());
+    init_commands::init_commands::set_guild_override(settings.discord.guilds.clone());
 
-    // get the publickey
-    let binding = settings.get::<Value>("discord").unwrap().to_owned().get("publickey").unwrap().to_owned();
-    let publickey= binding.as_str().unwrap();
-    // get the bot token
-    let binding = settings.get::<Value>("discord").unwrap().to_owned().get("token").unwrap().to_owned();
-    let token = binding.as_str().unwrap();
+    // a dry run only prints what would change about the registered commands
+    let dry_run = args.dry_run;
 
     // send commands to discord
-    init_commands::init_commands::load_cmds(&token);
+    init_commands::init_commands::load_cmds(token, dry_run);
+    if dry_run {
+        return Ok(());
+    }
+    // pick up changes to Commands.yml without restarting
+    init_commands::init_commands::watch_commands(token.to_string());
 
     // setup threads for handling interactions
 
     // create a channel to allow the endpoint to tell the handler about interactions
-    let (tx, rx): (Sender<Interaction>, Receiver<Interaction>) = mpsc::channel();
+    let (tx, rx): (Sender<DeferredCommand>, Receiver<DeferredCommand>) = mpsc::channel();
 
     // start the handler thread
     thread::spawn(|| {
@@ -59,10 +70,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 
     // start the webserver
-    unsafe {
-        interaction_endpoint::command_handler::main(address, port, publickey, tx)
-            .expect("There was an error that occurred when running the interactions endpoint.");
-    }
+    interaction_endpoint::command_handler::main(address, port, publickey, tx)
+        .expect("There was an error that occurred when running the interactions endpoint.");
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,170 @@
+pub mod mock_discord {
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use actix_web::web::Bytes;
+    use serde_json::{json, Value};
+
+    pub const APPLICATION_ID: &str = "100000000000000000";
+
+    #[derive(Debug, Clone)]
+    pub struct RecordedRequest {
+        pub method: String,
+        pub path: String,
+        pub authorization: Option<String>,
+        pub body: String
+    }
+
+    impl RecordedRequest {
+        pub fn json(&self) -> Value {
+            serde_json::from_str(self.body.as_str()).unwrap()
+        }
+    }
+
+    #[derive(Default)]
+    struct MockState {
+        requests: Vec<RecordedRequest>,
+        commands: Vec<Value>,
+        next_id: u64,
+        // how many of the next requests get a 429
+        rate_limited: u32,
+        // how many of the next requests get a 500
+        failing: u32
+    }
+
+    /// A stand-in for the discord api that records every request made to it
+    pub struct MockDiscord {
+        pub url: String,
+        state: Arc<Mutex<MockState>>
+    }
+
+    impl MockDiscord {
+        /// Starts the server on a random local port
+        pub fn start() -> MockDiscord {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/api", listener.local_addr().unwrap());
+            let state: Arc<Mutex<MockState>> = Arc::new(Mutex::new(MockState::default()));
+            let server_state = state.clone();
+            thread::spawn(move || {
+                actix_web::rt::System::new().block_on(async move {
+                    HttpServer::new(move || {
+                        App::new()
+                            .app_data(web::Data::new(server_state.clone()))
+                            .default_service(web::to(handle))
+                    })
+                        .workers(1)
+                        .listen(listener)
+                        .unwrap()
+                        .run()
+                        .await
+                })
+            });
+            MockDiscord { url, state }
+        }
+
+        pub fn requests(&self) -> Vec<RecordedRequest> {
+            self.state.lock().unwrap().requests.clone()
+        }
+
+        /// Answers the next `count` requests with a 429 asking to retry after 50ms
+        pub fn rate_limit_next(&self, count: u32) {
+            self.state.lock().unwrap().rate_limited = count;
+        }
+
+        /// Answers the next `count` requests with a 500
+        pub fn fail_next(&self, count: u32) {
+            self.state.lock().unwrap().failing = count;
+        }
+
+        /// Waits for a request with the given method whose path ends with `path_suffix`
+        pub fn wait_for(&self, method: &str, path_suffix: &str) -> Option<RecordedRequest> {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while Instant::now() < deadline {
+                let found = self.requests()
+                    .into_iter()
+                    .find(|request| request.method == method && request.path.ends_with(path_suffix));
+                if found.is_some() {
+                    return found;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            None
+        }
+    }
+
+    async fn handle(req: HttpRequest, body: Bytes, state: web::Data<Arc<Mutex<MockState>>>) -> HttpResponse {
+        let mut state = state.lock().unwrap();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        state.requests.push(RecordedRequest {
+            method: method.clone(),
+            path: path.clone(),
+            authorization: req.headers().get("Authorization").map(|value| value.to_str().unwrap().to_string()),
+            body: String::from_utf8(body.to_vec()).unwrap(),
+        });
+        if state.failing > 0 {
+            state.failing -= 1;
+            return HttpResponse::InternalServerError().finish();
+        }
+        if state.rate_limited > 0 {
+            state.rate_limited -= 1;
+            return HttpResponse::TooManyRequests()
+                .insert_header(("X-RateLimit-Bucket", "mock"))
+                .insert_header(("X-RateLimit-Remaining", "0"))
+                .insert_header(("X-RateLimit-Reset-After", "0.05"))
+                .json(json!({"message": "You are being rate limited.", "retry_after": 0.05, "global": false}));
+        }
+
+        let commands_path = format!("/api/applications/{}/commands", APPLICATION_ID);
+        let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        let response = match (method.as_str(), path.as_str()) {
+            ("GET", "/api/applications/@me") => json!({"id": APPLICATION_ID}),
+            ("GET", path) if path == commands_path => {
+                // like discord, only include the localizations when asked for them
+                let with_localizations = req.query_string().split('&').any(|pair| pair == "with_localizations=true");
+                let mut commands = state.commands.clone();
+                if !with_localizations {
+                    for command in commands.iter_mut().filter_map(|command| command.as_object_mut()) {
+                        command.remove("name_localizations");
+                        command.remove("description_localizations");
+                    }
+                }
+                Value::from(commands)
+            }
+            ("PUT", path) if path == commands_path => {
+                state.commands = vec![];
+                for command in body.as_array().cloned().unwrap_or_default() {
+                    register(&mut state, command);
+                }
+                Value::from(state.commands.clone())
+            }
+            ("POST", path) if path == commands_path => register(&mut state, body),
+            ("PATCH", path) if path.starts_with(commands_path.as_str()) => {
+                let id = path.rsplit('/').next().unwrap();
+                let command = state.commands.iter_mut().find(|command| command["id"] == id).unwrap();
+                for (key, value) in body.as_object().unwrap() {
+                    command[key] = value.clone();
+                }
+                command.clone()
+            }
+            ("DELETE", path) if path.starts_with(commands_path.as_str()) => {
+                let id = path.rsplit('/').next().unwrap();
+                state.commands.retain(|command| command["id"] != id);
+                return HttpResponse::NoContent().finish();
+            }
+            // webhook edits and follow-ups just echo the message back
+            _ => body
+        };
+        HttpResponse::Ok().json(response)
+    }
+
+    fn register(state: &mut MockState, mut command: Value) -> Value {
+        state.next_id += 1;
+        command["id"] = Value::from(state.next_id.to_string());
+        command["application_id"] = Value::from(APPLICATION_ID);
+        state.commands.push(command.clone());
+        command
+    }
+}
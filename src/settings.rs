@@ -0,0 +1,231 @@
+pub mod settings {
+    use std::env;
+    use std::fs::{self, File};
+    use config::Config;
+    use ed25519_dalek::PublicKey;
+    use serde::{Deserialize, Deserializer};
+    use serde::de::Error;
+    use crate::init_commands::init_commands::DEFAULT_API_URL;
+    use crate::interaction_endpoint::command_handler::DEFAULT_TIMESTAMP_WINDOW;
+
+    pub const DEFAULT_CONFIG_FILE: &str = "Config.yml";
+
+    // the environment variable overriding each setting
+    const ENV_OVERRIDES: [(&str, &str); 6] = [
+        ("discord.publickey", "SELFUSERBOT_DISCORD_PUBLICKEY"),
+        ("discord.token", "SELFUSERBOT_DISCORD_TOKEN"),
+        ("discord.api_url", "SELFUSERBOT_DISCORD_API_URL"),
+        ("webserver.address", "SELFUSERBOT_WEBSERVER_ADDRESS"),
+        ("webserver.port", "SELFUSERBOT_WEBSERVER_PORT"),
+        ("webserver.timestamp_window", "SELFUSERBOT_WEBSERVER_TIMESTAMP_WINDOW"),
+    ];
+    // the settings without a default, which come first in ENV_OVERRIDES
+    const REQUIRED_SETTINGS: usize = 2;
+    // a comma separated list of guild ids
+    const GUILDS_ENV: &str = "SELFUSERBOT_DISCORD_GUILDS";
+
+    pub const USAGE: &str = "Usage: SelfUserBot [options]
+  --config <path>     read the settings from this file instead of Config.yml
+  --address <address> listen on this address
+  --port <port>       listen on this port
+  --dry-run           only print what would change about the registered commands
+  --help              show this message
+
+Every setting can also be set with an environment variable, such as SELFUSERBOT_DISCORD_TOKEN";
+
+    /// Everything from Config.yml, with the environment and command line applied on top
+    #[derive(Debug, Deserialize)]
+    pub struct Settings {
+        pub discord: DiscordSettings,
+        #[serde(default)]
+        pub webserver: WebserverSettings
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct DiscordSettings {
+        // decoded here so a malformed key is reported like any other setting instead of failing on startup
+        #[serde(deserialize_with = "deserialize_publickey")]
+        pub publickey: PublicKey,
+        pub token: String,
+        // only needs changing to point the bot at something other than discord
+        #[serde(default = "default_api_url")]
+        pub api_url: String,
+        // every command is registered to these guilds instead of globally when not empty
+        #[serde(default)]
+        pub guilds: Vec<String>
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct WebserverSettings {
+        #[serde(default = "default_address")]
+        pub address: String,
+        #[serde(default = "default_port")]
+        pub port: u16,
+        // how many seconds the signed timestamp of an interaction may be off before it is rejected as a replay
+        #[serde(default = "default_timestamp_window")]
+        pub timestamp_window: u64
+    }
+
+    impl Default for WebserverSettings {
+        fn default() -> Self {
+            WebserverSettings {
+                address: default_address(),
+                port: default_port(),
+                timestamp_window: default_timestamp_window(),
+            }
+        }
+    }
+
+    fn deserialize_publickey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PublicKey, D::Error> {
+        let publickey = String::deserialize(deserializer)?;
+        hex::decode(publickey.trim()).ok()
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| D::Error::custom("discord.publickey has to be the 64 character public key from the discord developer portal"))
+    }
+
+    fn default_api_url() -> String {
+        DEFAULT_API_URL.to_string()
+    }
+
+    fn default_address() -> String {
+        "0.0.0.0".to_string()
+    }
+
+    fn default_port() -> u16 {
+        8080
+    }
+
+    fn default_timestamp_window() -> u64 {
+        DEFAULT_TIMESTAMP_WINDOW
+    }
+
+    /// What was passed on the command line
+    #[derive(Debug, PartialEq)]
+    pub struct Args {
+        pub config: String,
+        pub dry_run: bool,
+        pub help: bool,
+        // settings given as flags, keyed by their path in Config.yml
+        pub overrides: Vec<(String, String)>
+    }
+
+    /// Reads the flags, accepting both `--port 8080` and `--port=8080`
+    pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+        let mut parsed = Args { config: DEFAULT_CONFIG_FILE.to_string(), dry_run: false, help: false, overrides: vec![] };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None)
+            };
+            match flag.as_str() {
+                "--dry-run" => { parsed.dry_run = true }
+                "--help" | "-h" => { parsed.help = true }
+                "--config" | "--address" | "--port" => {
+                    let Some(value) = inline_value.or_else(|| args.next()) else {
+                        return Err(format!("{} needs a value", flag));
+                    };
+                    match flag.as_str() {
+                        "--config" => { parsed.config = value }
+                        "--address" => parsed.overrides.push(("webserver.address".to_string(), value)),
+                        _ => parsed.overrides.push(("webserver.port".to_string(), value))
+                    }
+                }
+                _ => return Err(format!("Unknown option '{}'", arg))
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Loads the settings from the config file, then the environment and then the command line,
+    /// returning a message naming the setting that is missing or malformed
+    pub fn load(args: &Args) -> Result<Settings, String> {
+        // create the config file if it doesn't already exist
+        if fs::metadata(&args.config).is_err() && File::create(&args.config).is_err() {
+            return Err(format!("Unable to create new config file {}. Please check file permissions", args.config));
+        }
+
+        let mut builder = Config::builder()
+            .add_source(config::File::with_name(args.config.as_str()));
+        for (key, variable) in ENV_OVERRIDES {
+            if let Ok(value) = env::var(variable) {
+                builder = builder.set_override(key, value).map_err(|err| err.to_string())?;
+            }
+        }
+        if let Ok(guilds) = env::var(GUILDS_ENV) {
+            let guilds: Vec<String> = guilds.split(',')
+                .map(|guild| guild.trim().to_string())
+                .filter(|guild| !guild.is_empty())
+                .collect();
+            builder = builder.set_override("discord.guilds", guilds).map_err(|err| err.to_string())?;
+        }
+        for (key, value) in &args.overrides {
+            builder = builder.set_override(key.as_str(), value.as_str()).map_err(|err| err.to_string())?;
+        }
+
+        let config = builder.build().map_err(|err| format!("Failed to read {}. {}", args.config, err))?;
+        // serde only names the field, so point at the whole key and how else it can be set
+        for (key, variable) in ENV_OVERRIDES.iter().take(REQUIRED_SETTINGS) {
+            if config.get::<config::Value>(key).is_err() {
+                return Err(format!("Missing setting {} in {}, it can also be set with {}", key, args.config, variable));
+            }
+        }
+        config.try_deserialize::<Settings>()
+            .map_err(|err| format!("Invalid settings in {}: {}", args.config, err))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{env, fs};
+        use super::{load, parse_args, Args, DEFAULT_CONFIG_FILE};
+
+        fn args(args: &[&str]) -> Result<Args, String> {
+            parse_args(args.iter().map(|arg| arg.to_string()))
+        }
+
+        #[test]
+        fn parses_flags() {
+            let parsed = args(&["--config", "other.yml", "--port=9000", "--dry-run"]).unwrap();
+            assert_eq!(parsed, Args {
+                config: "other.yml".to_string(),
+                dry_run: true,
+                help: false,
+                overrides: vec![("webserver.port".to_string(), "9000".to_string())],
+            });
+            assert_eq!(args(&[]).unwrap().config, DEFAULT_CONFIG_FILE);
+            assert_eq!(args(&["--port"]).unwrap_err(), "--port needs a value");
+            assert_eq!(args(&["--verbose"]).unwrap_err(), "Unknown option '--verbose'");
+        }
+
+        #[test]
+        fn applies_defaults_and_overrides() {
+            let path = env::temp_dir().join("selfuserbot-settings-test.yml");
+            let publickey = "publickey: 1fd2fbb4d1c1cf7ba9bb3d3e1a0e1d2b4a5c9d8e7f6a5b4c3d2e1f0a9b8c7d6e";
+            fs::write(&path, format!("discord:\n  {}\n  token: from-file\n", publickey)).unwrap();
+            let config = path.to_str().unwrap();
+
+            let settings = load(&args(&["--config", config]).unwrap()).unwrap();
+            assert_eq!(settings.discord.token, "from-file");
+            assert_eq!(settings.discord.api_url, "https://discord.com/api");
+            assert_eq!(settings.webserver.port, 8080);
+
+            env::set_var("SELFUSERBOT_DISCORD_TOKEN", "from-env");
+            let settings = load(&args(&["--config", config, "--port", "9000"]).unwrap()).unwrap();
+            env::remove_var("SELFUSERBOT_DISCORD_TOKEN");
+            assert_eq!(settings.discord.token, "from-env");
+            assert_eq!(settings.webserver.port, 9000);
+
+            let error = load(&args(&["--config", config, "--port", "lots"]).unwrap()).unwrap_err();
+            assert!(error.contains("webserver.port"), "{}", error);
+
+            fs::write(&path, "discord:\n  publickey: key\n  token: from-file\n").unwrap();
+            let error = load(&args(&["--config", config]).unwrap()).unwrap_err();
+            assert!(error.starts_with("Invalid settings in"), "{}", error);
+            assert!(error.contains("discord.publickey has to be the 64 character public key"), "{}", error);
+
+            fs::write(&path, "discord:\n  token: from-file\n").unwrap();
+            let error = load(&args(&["--config", config]).unwrap()).unwrap_err();
+            assert!(error.starts_with("Missing setting discord.publickey"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+pub mod sync_commands {
+    use reqwest::blocking::{Client, RequestBuilder};
+    use crate::discord_client::discord_client::DiscordClient;
+    use serde_json::{Map, Value};
+
+    // the fields of a command we manage, anything else discord returns is ignored when comparing
+    const MANAGED_FIELDS: [&str; 7] = [
+        "name", "description", "name_localizations", "description_localizations", "options", "integration_types", "contexts"
+    ];
+
+    /// An update to an already registered command
+    pub struct CommandUpdate {
+        pub id: String,
+        pub name: String,
+        pub body: Value,
+        // field name, registered value, local value
+        pub changes: Vec<(String, Value, Value)>
+    }
+
+    /// Everything that has to happen for discord to match the local commands
+    pub struct SyncPlan {
+        pub create: Vec<Value>,
+        pub update: Vec<CommandUpdate>,
+        // id and name of each command to delete
+        pub delete: Vec<(String, String)>,
+        pub unchanged: usize
+    }
+
+    impl SyncPlan {
+        pub fn is_empty(&self) -> bool {
+            self.create.is_empty() && self.update.is_empty() && self.delete.is_empty()
+        }
+
+        pub fn print(&self, scope: &str) {
+            if self.is_empty() {
+                println!("All {} {} commands are up to date.", self.unchanged, scope);
+                return;
+            }
+            println!("Sync plan for {} commands ({} unchanged):", scope, self.unchanged);
+            for body in &self.create {
+                println!("  + create {}", body["name"].as_str().unwrap_or_default());
+            }
+            for update in &self.update {
+                let fields: Vec<&str> = update.changes.iter().map(|(field, _, _)| field.as_str()).collect();
+                println!("  ~ update {} ({})", update.name, fields.join(", "));
+                for (field, old, new) in &update.changes {
+                    println!("      {}: {} -> {}", field, old, new);
+                }
+            }
+            for (_, name) in &self.delete {
+                println!("  - delete {}", name);
+            }
+        }
+    }
+
+    /// Gets the commands that are currently registered at the given commands url.
+    /// Discord leaves out the localizations unless asked, which would make every localized command look changed
+    pub fn fetch_registered(client: &DiscordClient, auth_header: &str, url: &str) -> Result<Vec<Value>, String> {
+        let response = client.send(|client| client.get(url).query(&[("with_localizations", "true")]).header("Authorization", auth_header))
+            .map_err(|err| format!("{:?}", err))?;
+        if !response.status().is_success() {
+            return Err(format!("Discord returned {} {}", response.status(), response.text().unwrap_or_default()));
+        }
+        let json: Value = response.json().map_err(|err| format!("{:?}", err))?;
+        match json {
+            Value::Array(commands) => Ok(commands),
+            _ => Err("Discord did not return a list of commands".to_string())
+        }
+    }
+
+    /// Compares the local command bodies against the registered ones
+    pub fn plan(local: Vec<Value>, registered: Vec<Value>) -> SyncPlan {
+        let mut plan = SyncPlan { create: vec![], update: vec![], delete: vec![], unchanged: 0 };
+        let mut remaining: Vec<Value> = registered;
+        for body in local {
+            let position = remaining.iter().position(|command| same_command(command, &body));
+            let Some(position) = position else {
+                plan.create.push(body);
+                continue;
+            };
+            let command = remaining.remove(position);
+            let changes = changed_fields(&command, &body);
+            if changes.is_empty() {
+                plan.unchanged += 1;
+            } else {
+                plan.update.push(CommandUpdate {
+                    id: command["id"].as_str().unwrap_or_default().to_string(),
+                    name: command["name"].as_str().unwrap_or_default().to_string(),
+                    body,
+                    changes,
+                });
+            }
+        }
+        for command in remaining {
+            plan.delete.push((
+                command["id"].as_str().unwrap_or_default().to_string(),
+                command["name"].as_str().unwrap_or_default().to_string()
+            ));
+        }
+        plan
+    }
+
+    /// Carries out the plan against the given commands url, returning whether every request succeeded
+    pub fn apply(client: &DiscordClient, auth_header: &str, url: &str, plan: &SyncPlan) -> bool {
+        let mut success = true;
+        for body in &plan.create {
+            let request = |client: &Client| client.post(url).json(body);
+            success &= send(client, request, "create", body["name"].as_str().unwrap_or_default(), auth_header);
+        }
+        for update in &plan.update {
+            let request = |client: &Client| client.patch(format!("{}/{}", url, update.id)).json(&update.body);
+            success &= send(client, request, "update", update.name.as_str(), auth_header);
+        }
+        for (id, name) in &plan.delete {
+            let request = |client: &Client| client.delete(format!("{}/{}", url, id));
+            success &= send(client, request, "delete", name.as_str(), auth_header);
+        }
+        success
+    }
+
+    fn send<F>(client: &DiscordClient, request: F, action: &str, name: &str, auth_header: &str) -> bool where F: Fn(&Client) -> RequestBuilder {
+        match client.send(|client| request(client).header("Authorization", auth_header)) {
+            Ok(resp) => {
+                if resp.status().is_success() {
+                    println!("Discord accepted the {} of command {}", action, name);
+                    true
+                } else {
+                    println!("Discord returned an error upon the {} of command {}.", action, name);
+                    println!("{:#?}", resp.text());
+                    false
+                }
+            }
+            Err(err) => {
+                println!("An error occurred whilst trying to {} command {}! Error: {:?}", action, name, err);
+                false
+            }
+        }
+    }
+
+    fn same_command(registered: &Value, local: &Value) -> bool {
+        registered["name"] == local["name"] && command_type(registered) == command_type(local)
+    }
+
+    fn command_type(command: &Value) -> u64 {
+        command["type"].as_u64().unwrap_or(1)
+    }
+
+    fn changed_fields(registered: &Value, local: &Value) -> Vec<(String, Value, Value)> {
+        let mut changes = vec![];
+        for field in MANAGED_FIELDS {
+            let old = normalize_field(registered.get(field));
+            let new = normalize_field(local.get(field));
+            if old != new {
+                changes.push((field.to_string(), old, new));
+            }
+        }
+        changes
+    }
+
+    fn normalize_field(value: Option<&Value>) -> Value {
+        let value = normalize(value.cloned().unwrap_or(Value::Null));
+        if is_default(&value) { Value::Null } else { value }
+    }
+
+    /// Discord leaves out or nulls values that are at their default, so drop them on both sides
+    /// and compare every number as a float
+    fn normalize(value: Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut normalized = Map::new();
+                for (key, value) in map {
+                    let value = normalize(value);
+                    if !is_default(&value) {
+                        normalized.insert(key, value);
+                    }
+                }
+                Value::Object(normalized)
+            }
+            Value::Array(values) => Value::Array(values.into_iter().map(normalize).collect()),
+            Value::Number(number) => Value::from(number.as_f64().unwrap_or_default()),
+            value => value
+        }
+    }
+
+    fn is_default(value: &Value) -> bool {
+        match value {
+            Value::Null => true,
+            Value::Bool(value) => !value,
+            Value::Array(values) => values.is_empty(),
+            Value::Object(map) => map.is_empty(),
+            _ => false
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::{json, Value};
+        use super::plan;
+
+        // a command as discord returns it from GET /applications/{id}/commands
+        fn registered_search() -> Value {
+            json!({
+                "id": "1001",
+                "application_id": "42",
+                "version": "1200",
+                "default_member_permissions": null,
+                "type": 1,
+                "name": "search",
+                "name_localizations": null,
+                "description": "Search the docs",
+                "description_localizations": null,
+                "dm_permission": true,
+                "contexts": [0, 1, 2],
+                "integration_types": [1],
+                "nsfw": false,
+                "options": [{
+                    "type": 4,
+                    "name": "limit",
+                    "description": "How many results",
+                    "required": false,
+                    "min_value": 1,
+                    "max_value": 10
+                }]
+            })
+        }
+
+        fn local_search() -> Value {
+            json!({
+                "name": "search",
+                "description": "Search the docs",
+                "integration_types": [1],
+                "contexts": [0, 1, 2],
+                "options": [{"type": 4, "name": "limit", "description": "How many results", "min_value": 1.0, "max_value": 10.0}]
+            })
+        }
+
+        #[test]
+        fn leaves_matching_commands_alone() {
+            // defaults discord fills in and integers registered as floats are not changes
+            let plan = plan(vec![local_search()], vec![registered_search()]);
+            assert!(plan.is_empty());
+            assert_eq!(plan.unchanged, 1);
+        }
+
+        #[test]
+        fn plans_creates_updates_and_deletes() {
+            let mut changed = local_search();
+            changed["description"] = Value::from("Search everything");
+            let user_menu = json!({"name": "search", "type": 2, "integration_types": [1], "contexts": [0]});
+            let mut stale = registered_search();
+            stale["id"] = Value::from("1002");
+            stale["name"] = Value::from("old");
+
+            let plan = plan(vec![changed, user_menu], vec![registered_search(), stale]);
+            // a user command shares the name of the slash command but is a different command
+            assert_eq!(plan.create.len(), 1);
+            assert_eq!(plan.create[0]["type"], 2);
+            assert_eq!(plan.update.len(), 1);
+            assert_eq!(plan.update[0].id, "1001");
+            let fields: Vec<&str> = plan.update[0].changes.iter().map(|(field, _, _)| field.as_str()).collect();
+            assert_eq!(fields, vec!["description"]);
+            assert_eq!(plan.delete, vec![("1002".to_string(), "old".to_string())]);
+        }
+    }
+}
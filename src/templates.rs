@@ -0,0 +1,357 @@
+pub mod templates {
+    use std::collections::HashMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::init_commands::init_commands::{ActionRow, ButtonComponent, Component, EmbedAuthor, EmbedField, EmbedFooter, EmbedImage, EmbedThumbnail, EmbedVideo, MessageData, MessageEmbed, Modal, SelectComponent, SelectOption, TextInput};
+    use crate::interaction_endpoint::command_handler::{Interaction, User};
+
+    // placeholders in these namespaces render as nothing when missing, anything else is left untouched
+    const NAMESPACES: [&str; 10] = ["user", "option", "channel", "guild", "locale", "now", "interaction", "target", "field", "select"];
+
+    struct TemplateValue {
+        value: String,
+        user_supplied: bool
+    }
+
+    /// The values placeholders such as `{user.name}` are rendered against
+    pub struct TemplateContext {
+        values: HashMap<String, TemplateValue>
+    }
+
+    impl TemplateContext {
+        pub fn new() -> TemplateContext {
+            TemplateContext { values: HashMap::new() }
+        }
+
+        /// Builds the context for a single invocation of a command
+        pub fn from_interaction(interaction: &Interaction) -> TemplateContext {
+            let mut context = TemplateContext::new();
+            context.insert("interaction.id", interaction.id.clone());
+            if let Some(user) = interaction.invoking_user() {
+                context.insert_user("user", user);
+            }
+            if let Some(channel_id) = &interaction.channel_id {
+                context.insert("channel.id", channel_id.clone());
+            }
+            if let Some(guild_id) = &interaction.guild_id {
+                context.insert("guild.id", guild_id.clone());
+            }
+            if let Some(locale) = &interaction.locale {
+                context.insert("locale", locale.clone());
+            }
+            if let Some(data) = &interaction.data {
+                for (name, value) in data.option_values() {
+                    context.insert_user_supplied(format!("option.{}", name).as_str(), value);
+                }
+                // the values submitted in a modal
+                for (custom_id, value) in data.submitted_values() {
+                    context.insert_user_supplied(format!("field.{}", custom_id).as_str(), value);
+                }
+                // the values chosen in a select menu
+                if let Some(values) = &data.values {
+                    context.insert("select.value", values.first().cloned().unwrap_or_default());
+                    context.insert("select.values", values.join(", "));
+                    context.insert("select.mentions", data.selected_mentions().join(", "));
+                }
+                // the target of a context menu command
+                if let Some(target_id) = &data.target_id {
+                    context.insert("target.id", target_id.clone());
+                }
+                if let Some(user) = data.target_user() {
+                    context.insert_user("target.user", user);
+                }
+                if let Some(message) = data.target_message() {
+                    let guild = interaction.guild_id.clone().unwrap_or("@me".to_string());
+                    context.insert("target.message.id", message.id.clone());
+                    context.insert("target.message.link", format!("https://discord.com/channels/{}/{}/{}", guild, message.channel_id, message.id));
+                    context.insert("target.message.timestamp", message.timestamp.clone());
+                    context.insert_user_supplied("target.message.content", message.content.clone());
+                    context.insert_user("target.message.author", &message.author);
+                }
+            }
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+            context.insert("now", format_timestamp(now));
+            context.insert("now.unix", now.to_string());
+            context
+        }
+
+        /// Inserts the details of a user under the given prefix, e.g. `user.name`
+        fn insert_user(&mut self, prefix: &str, user: &User) {
+            self.insert(format!("{}.id", prefix).as_str(), user.id.clone());
+            self.insert(format!("{}.mention", prefix).as_str(), format!("<@{}>", user.id));
+            self.insert(format!("{}.avatar", prefix).as_str(), user.avatar_url());
+            self.insert_user_supplied(format!("{}.name", prefix).as_str(), user.display_name().to_string());
+            self.insert_user_supplied(format!("{}.username", prefix).as_str(), user.username.clone());
+        }
+
+        pub fn insert(&mut self, key: &str, value: String) {
+            self.values.insert(key.to_string(), TemplateValue { value, user_supplied: false });
+        }
+
+        /// Inserts a value that came from a user, which is markdown escaped unless a template says otherwise
+        pub fn insert_user_supplied(&mut self, key: &str, value: String) {
+            self.values.insert(key.to_string(), TemplateValue { value, user_supplied: true });
+        }
+
+        /// Renders text that is shown as markdown
+        pub fn render(&self, template: &str) -> String {
+            self.render_template(template, true)
+        }
+
+        /// Renders text that is not markdown, such as urls, where escaping would only break it
+        pub fn render_plain(&self, template: &str) -> String {
+            self.render_template(template, false)
+        }
+
+        fn render_template(&self, template: &str, markdown: bool) -> String {
+            let mut output = String::with_capacity(template.len());
+            let mut rest = template;
+            while let Some(index) = rest.find(['{', '}']) {
+                output.push_str(&rest[..index]);
+                let brace = &rest[index..index + 1];
+                rest = &rest[index + 1..];
+                // doubled braces are an escaped literal brace
+                if rest.starts_with(brace) {
+                    output.push_str(brace);
+                    rest = &rest[1..];
+                    continue;
+                }
+                if brace == "}" {
+                    output.push('}');
+                    continue;
+                }
+                match rest.find('}') {
+                    Some(end) => {
+                        match self.render_placeholder(&rest[..end], markdown) {
+                            Some(rendered) => output.push_str(rendered.as_str()),
+                            None => {
+                                output.push('{');
+                                output.push_str(&rest[..=end]);
+                            }
+                        }
+                        rest = &rest[end + 1..];
+                    }
+                    None => output.push('{')
+                }
+            }
+            output.push_str(rest);
+            output
+        }
+
+        /// Renders the inside of a `{...}` placeholder, returning None when it isn't one
+        fn render_placeholder(&self, placeholder: &str, markdown: bool) -> Option<String> {
+            let mut parts = placeholder.split('|');
+            let key = parts.next().unwrap().trim();
+            let valid_key = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '_' || c == '-');
+            if !valid_key {
+                return None;
+            }
+            let (mut value, user_supplied) = match self.values.get(key) {
+                Some(value) => (value.value.clone(), value.user_supplied),
+                None => {
+                    let namespace = key.split('.').next().unwrap();
+                    if !NAMESPACES.contains(&namespace) {
+                        return None;
+                    }
+                    (String::new(), false)
+                }
+            };
+
+            let mut escape = markdown && user_supplied;
+            for filter in parts {
+                let filter = filter.trim();
+                let (name, argument) = match filter.find('(') {
+                    Some(open) if filter.ends_with(')') => (&filter[..open], Some(&filter[open + 1..filter.len() - 1])),
+                    _ => (filter, None)
+                };
+                match name {
+                    "upper" => { value = value.to_uppercase() }
+                    "lower" => { value = value.to_lowercase() }
+                    "urlencode" => {
+                        value = url_encode(value.as_str());
+                        escape = false;
+                    }
+                    "truncate" => {
+                        if let Some(length) = argument.and_then(|argument| argument.trim().parse::<usize>().ok()) {
+                            value = value.chars().take(length).collect();
+                        }
+                    }
+                    "default" => {
+                        if value.is_empty() {
+                            value = argument.unwrap_or("").to_string();
+                            escape = false;
+                        }
+                    }
+                    "escape" => { escape = true }
+                    "raw" => { escape = false }
+                    _ => { println!("Unknown template filter '{}' in '{{{}}}'", name, placeholder) }
+                }
+            }
+            if escape {
+                value = escape_markdown(value.as_str());
+            }
+            Some(value)
+        }
+    }
+
+    /// Renders the title and inputs of a modal against the context, so inputs can be prefilled
+    pub fn render_modal(modal: &Modal, context: &TemplateContext) -> Modal {
+        Modal {
+            title: context.render_plain(modal.title.as_str()),
+            inputs: modal.inputs.iter().map(|input| TextInput {
+                label: context.render_plain(input.label.as_str()),
+                placeholder: input.placeholder.as_ref().map(|placeholder| context.render_plain(placeholder.as_str())),
+                value: input.value.as_ref().map(|value| context.render_plain(value.as_str())),
+                ..input.clone()
+            }).collect(),
+        }
+    }
+
+    /// Renders every piece of text in a message against the context
+    pub fn render_message(message: &MessageData, context: &TemplateContext) -> MessageData {
+        MessageData {
+            content: context.render(message.content.as_str()),
+            embeds: message.embeds.iter().map(|embed| render_embed(embed, context)).collect(),
+            components: message.components.iter().map(|row| ActionRow {
+                r#type: row.r#type,
+                components: row.components.iter().map(|component| match component {
+                    Component::Button(button) => Component::Button(ButtonComponent {
+                        label: context.render_plain(button.label.as_str()),
+                        url: button.url.as_ref().map(|url| context.render_plain(url.as_str())),
+                        ..button.clone()
+                    }),
+                    Component::Select(select) => Component::Select(SelectComponent {
+                        placeholder: select.placeholder.as_ref().map(|placeholder| context.render_plain(placeholder.as_str())),
+                        options: select.options.iter().map(|option| SelectOption {
+                            label: context.render_plain(option.label.as_str()),
+                            description: option.description.as_ref().map(|description| context.render_plain(description.as_str())),
+                            ..option.clone()
+                        }).collect(),
+                        ..select.clone()
+                    })
+                }).collect(),
+            }).collect(),
+            flags: message.flags,
+            files: message.files.clone(),
+        }
+    }
+
+    fn render_embed(embed: &MessageEmbed, context: &TemplateContext) -> MessageEmbed {
+        let render = |text: &Option<String>| text.as_ref().map(|text| context.render(text.as_str()));
+        let render_url = |url: &Option<String>| url.as_ref().map(|url| context.render_plain(url.as_str()));
+        MessageEmbed {
+            title: render(&embed.title),
+            description: render(&embed.description),
+            url: render_url(&embed.url),
+            color: embed.color,
+            footer: embed.footer.as_ref().map(|footer| EmbedFooter {
+                text: context.render(footer.text.as_str()),
+                icon_url: render_url(&footer.icon_url),
+            }),
+            image: embed.image.as_ref().map(|image| EmbedImage { url: context.render_plain(image.url.as_str()) }),
+            thumbnail: embed.thumbnail.as_ref().map(|thumbnail| EmbedThumbnail { url: context.render_plain(thumbnail.url.as_str()) }),
+            video: embed.video.as_ref().map(|video| EmbedVideo { url: context.render_plain(video.url.as_str()) }),
+            author: embed.author.as_ref().map(|author| EmbedAuthor {
+                name: context.render(author.name.as_str()),
+                url: render_url(&author.url),
+                icon_url: render_url(&author.icon_url),
+            }),
+            fields: embed.fields.as_ref().map(|fields| fields.iter().map(|field| EmbedField {
+                name: context.render(field.name.as_str()),
+                value: context.render(field.value.as_str()),
+                inline: field.inline,
+            }).collect()),
+        }
+    }
+
+    /// Backslash escapes every character Discord treats as markdown or a mention
+    pub fn escape_markdown(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '<' | '#' | '-' | '[' | ']' | '(' | ')' | '@') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Percent encodes everything except the unreserved url characters
+    pub fn url_encode(text: &str) -> String {
+        let mut encoded = String::with_capacity(text.len());
+        for byte in text.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(format!("%{:02X}", byte).as_str());
+            }
+        }
+        encoded
+    }
+
+    /// Formats a unix timestamp as an ISO 8601 UTC date and time
+    fn format_timestamp(timestamp: u64) -> String {
+        let days = (timestamp / 86400) as i64;
+        let seconds = timestamp % 86400;
+        // convert days since the epoch to a civil date (Howard Hinnant's algorithm)
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{format_timestamp, TemplateContext};
+
+        fn context() -> TemplateContext {
+            let mut context = TemplateContext::new();
+            context.insert("user.id", "42".to_string());
+            context.insert_user_supplied("user.name", "*bold* _name_".to_string());
+            context.insert_user_supplied("option.query", "rust & go".to_string());
+            context
+        }
+
+        #[test]
+        fn renders_placeholders_and_braces() {
+            let context = context();
+            assert_eq!(context.render("id {user.id}"), "id 42");
+            assert_eq!(context.render("{{user.id}} {{ }}"), "{user.id} { }");
+            assert_eq!(context.render("a } b {"), "a } b {");
+            // missing values in known namespaces are empty, unknown namespaces are left alone
+            assert_eq!(context.render("[{guild.id}]"), "[]");
+            assert_eq!(context.render("{unknown.key} {not a placeholder}"), "{unknown.key} {not a placeholder}");
+        }
+
+        #[test]
+        fn applies_filters() {
+            let context = context();
+            assert_eq!(context.render("{option.query|upper}"), "RUST & GO");
+            assert_eq!(context.render("{option.query | truncate(4)}"), "rust");
+            assert_eq!(context.render_plain("?q={option.query|urlencode}"), "?q=rust%20%26%20go");
+            assert_eq!(context.render("{guild.id|default(DM)}"), "DM");
+            assert_eq!(context.render("{user.id|lower|nonsense}"), "42");
+        }
+
+        #[test]
+        fn escapes_user_supplied_values() {
+            let context = context();
+            assert_eq!(context.render("{user.name}"), "\\*bold\\* \\_name\\_");
+            assert_eq!(context.render("{user.name|raw}"), "*bold* _name_");
+            assert_eq!(context.render_plain("{user.name}"), "*bold* _name_");
+            // escaping happens after the other filters, so truncating can't cut an escape in half
+            assert_eq!(context.render("{user.name|truncate(1)}"), "\\*");
+        }
+
+        #[test]
+        fn formats_timestamps() {
+            assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+            assert_eq!(format_timestamp(1709210096), "2024-02-29T12:34:56Z");
+        }
+    }
+}
@@ -0,0 +1,180 @@
+pub mod variants {
+    use std::collections::HashMap;
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::RwLock;
+    use std::time::{Duration, Instant};
+    use lazy_static::lazy_static;
+    use crate::init_commands::init_commands::{CommandResponse, MessageData};
+
+    lazy_static! {
+        // the variant each user last got per command and when, keyed by command type and path and user id
+        static ref LAST_VARIANTS: RwLock<HashMap<(String, String), (usize, Instant)>> = RwLock::new(HashMap::new());
+    }
+
+    // how long a variant is kept from being picked again for the same user
+    const LAST_VARIANT_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Picks one of the command's variants by weight, returning None when there is none to pick
+    /// and the command answers with its own message
+    pub fn pick(command: &CommandResponse, command_key: &str, user_id: Option<&str>) -> Option<usize> {
+        if command.variants.is_empty() {
+            return None;
+        }
+        let key = (command_key.to_string(), user_id.unwrap_or_default().to_string());
+        let last = if command.no_repeat {
+            LAST_VARIANTS.read().unwrap().get(&key)
+                .filter(|(_, picked)| picked.elapsed() <= LAST_VARIANT_EXPIRY)
+                .map(|(index, _)| *index)
+        } else {
+            None
+        };
+
+        // leave out the previous variant as long as there is something else to pick
+        let candidates: Vec<usize> = (0..command.variants.len())
+            .filter(|index| command.variants[*index].weight > 0)
+            .collect();
+        let candidates: Vec<usize> = match candidates.iter().filter(|index| Some(**index) != last).copied().collect::<Vec<usize>>() {
+            others if !others.is_empty() => others,
+            _ => candidates
+        };
+        let total: u64 = candidates.iter().map(|index| command.variants[*index].weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = random() % total;
+        let mut picked = candidates[0];
+        for index in candidates {
+            let weight = command.variants[index].weight as u64;
+            if roll < weight {
+                picked = index;
+                break;
+            }
+            roll -= weight;
+        }
+        if command.no_repeat {
+            let mut last_variants = LAST_VARIANTS.write().unwrap();
+            last_variants.retain(|_, (_, picked)| picked.elapsed() <= LAST_VARIANT_EXPIRY);
+            last_variants.insert(key, (picked, Instant::now()));
+        }
+        Some(picked)
+    }
+
+    /// The message to respond with in the user's locale, from the picked variant if there is one.
+    /// Variants are translated through their own locales, the command's locales only apply to its own message
+    pub fn message<'a>(command: &'a CommandResponse, variant: Option<usize>, locale: Option<&str>) -> &'a MessageData {
+        match variant.and_then(|index| command.variants.get(index)) {
+            Some(variant) => variant.message_for(locale),
+            None => command.message_for(locale)
+        }
+    }
+
+    // every RandomState is seeded differently, which is random enough to pick a message
+    fn random() -> u64 {
+        RandomState::new().build_hasher().finish()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{env, fs};
+        use crate::init_commands::init_commands::{read_commands, CommandResponse};
+        use super::{message, pick, LAST_VARIANTS};
+
+        fn command(name: &str, yaml: &str) -> CommandResponse {
+            let path = env::temp_dir().join(format!("selfuserbot-{}-commands.yml", name));
+            fs::write(&path, yaml).unwrap();
+            read_commands(path.to_str().unwrap()).unwrap().remove(0)
+        }
+
+        #[test]
+        fn picks_by_weight() {
+            let command = command("weighted", "
+roll:
+  description: 'Roll'
+  variants:
+    1:
+      weight: 3
+      content: 'often'
+    2:
+      content: 'sometimes'
+    3:
+      weight: 0
+      content: 'never'
+");
+            let mut picks = [0; 3];
+            for _ in 0..4000 {
+                picks[pick(&command, "1:roll", Some("1")).unwrap()] += 1;
+            }
+            // three to one, with plenty of room for chance
+            assert!((2700..3300).contains(&picks[0]), "{:?}", picks);
+            assert_eq!(picks[2], 0);
+        }
+
+        #[test]
+        fn never_repeats_for_the_same_user() {
+            let command = command("no-repeat", "
+flip:
+  description: 'Flip'
+  no_repeat: true
+  variants:
+    1:
+      content: 'heads'
+    2:
+      content: 'tails'
+");
+            let mut last = pick(&command, "1:flip", Some("1"));
+            for _ in 0..50 {
+                let picked = pick(&command, "1:flip", Some("1"));
+                assert_ne!(picked, last);
+                last = picked;
+            }
+        }
+
+        #[test]
+        fn remembers_subcommands_by_their_full_path() {
+            let command = command("subcommand-variants", "
+coin:
+  description: 'Coin'
+  subcommands:
+    flip:
+      description: 'Flip'
+      no_repeat: true
+      variants:
+        1:
+          content: 'heads'
+        2:
+          content: 'tails'
+").subcommands.remove(0);
+            let first = pick(&command, "1:coin/flip", Some("2"));
+            let second = pick(&command, "1:dice/flip", Some("2"));
+            let last_variants = LAST_VARIANTS.read().unwrap();
+            assert_eq!(last_variants.get(&("1:coin/flip".to_string(), "2".to_string())).map(|(index, _)| *index), first);
+            assert_eq!(last_variants.get(&("1:dice/flip".to_string(), "2".to_string())).map(|(index, _)| *index), second);
+        }
+
+        #[test]
+        fn translates_variants_through_their_own_locales() {
+            let command = command("localized-variants", "
+greet:
+  description: 'Greet'
+  content: 'Hello'
+  locales:
+    de:
+      content: 'Hallo'
+  variants:
+    1:
+      content: 'Hi'
+      ephemeral: true
+      locales:
+        de:
+          content: 'Servus'
+");
+            let variant = pick(&command, "1:greet", None);
+            assert_eq!(message(&command, variant, Some("de")).content, "Servus");
+            assert_eq!(message(&command, variant, Some("de")).flags, 64);
+            assert_eq!(message(&command, variant, Some("fr")).content, "Hi");
+            assert_eq!(message(&command, None, Some("de")).content, "Hallo");
+        }
+    }
+}